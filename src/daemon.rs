@@ -0,0 +1,113 @@
+//! Daemon mode: periodically rechecks proxies stored in the database
+
+use crate::database::ProxyDatabase;
+use crate::proxy::{AdaptiveScheduler, ProxyChecker};
+use crate::Result;
+use chrono::Utc;
+use std::time::Duration;
+use tokio_util::sync::CancellationToken;
+
+/// Default pause between scans for proxies that are due
+const DEFAULT_TICK: Duration = Duration::from_secs(30);
+
+/// Rechecks stored proxies on intervals chosen by an [`AdaptiveScheduler`]
+pub struct Daemon {
+    db: ProxyDatabase,
+    checker: ProxyChecker,
+    scheduler: AdaptiveScheduler,
+    tick: Duration,
+    shutdown: CancellationToken,
+}
+
+impl Daemon {
+    pub fn new(db: ProxyDatabase, checker: ProxyChecker, scheduler: AdaptiveScheduler) -> Self {
+        Self {
+            db,
+            checker,
+            scheduler,
+            tick: DEFAULT_TICK,
+            shutdown: CancellationToken::new(),
+        }
+    }
+
+    /// Set how often the database is scanned for due proxies
+    pub fn with_tick(mut self, tick: Duration) -> Self {
+        self.tick = tick;
+        self
+    }
+
+    /// Stop [`Daemon::run`] once `shutdown` is cancelled
+    pub fn with_shutdown(mut self, shutdown: CancellationToken) -> Self {
+        self.shutdown = shutdown;
+        self
+    }
+
+    /// Token that stops [`Daemon::run`] when cancelled
+    pub fn shutdown_token(&self) -> CancellationToken {
+        self.shutdown.clone()
+    }
+
+    /// Check every proxy that is due, record the results and schedule the next checks
+    ///
+    /// Returns the number of proxies checked.
+    pub async fn run_once(&self) -> Result<usize> {
+        let due: Vec<_> = self
+            .db
+            .get_due_proxies(Utc::now())
+            .await?
+            .into_iter()
+            .map(|record| record.proxy)
+            .collect();
+
+        if due.is_empty() {
+            return Ok(0);
+        }
+
+        let results = self.checker.check_proxies(due).await;
+        for result in &results {
+            self.db.record_result(result).await?;
+
+            let history = self
+                .db
+                .get_recent_history(&result.proxy, self.scheduler.history_window)
+                .await?;
+            let interval = self.scheduler.next_interval(&history);
+            let next_check = Utc::now() + chrono::Duration::from_std(interval)?;
+            self.db.set_next_check(&result.proxy, next_check).await?;
+        }
+
+        Ok(results.len())
+    }
+
+    /// Recheck due proxies every tick until the shutdown token is cancelled
+    ///
+    /// `on_checked` is called with the number of proxies rechecked after each
+    /// tick that found some due. A batch already being checked when shutdown
+    /// is requested is finished and recorded; then the database is closed so
+    /// pending writes are flushed. The database is closed on errors too.
+    pub async fn run<F>(&self, on_checked: F) -> Result<()>
+    where
+        F: FnMut(usize),
+    {
+        let result = self.run_ticks(on_checked).await;
+        self.db.close().await;
+        result
+    }
+
+    async fn run_ticks<F>(&self, mut on_checked: F) -> Result<()>
+    where
+        F: FnMut(usize),
+    {
+        while !self.shutdown.is_cancelled() {
+            let checked = self.run_once().await?;
+            if checked > 0 {
+                on_checked(checked);
+            }
+            tokio::select! {
+                _ = tokio::time::sleep(self.tick) => {}
+                _ = self.shutdown.cancelled() => {}
+            }
+        }
+        Ok(())
+    }
+}
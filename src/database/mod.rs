@@ -2,6 +2,27 @@ use crate::models::Todo;
 use crate::Result;
 use sqlx::{sqlite::SqlitePoolOptions, SqlitePool};
 
+mod proxy_db;
+
+pub use proxy_db::{ProxyDatabase, ProxyRecord};
+
+/// Convert a database path (or `:memory:`) into a SQLite connection URL
+pub(crate) fn sqlite_url(database_url: &str) -> Result<String> {
+    // Handle special cases for SQLite URL format
+    let url = match database_url {
+        ":memory:" => "sqlite::memory:".to_string(),
+        path if path.starts_with("sqlite://") => path.to_string(),
+        path => {
+            // Create parent directory if needed for file databases
+            if let Some(parent) = std::path::Path::new(path).parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            format!("sqlite://{}?mode=rwc", path)
+        }
+    };
+    Ok(url)
+}
+
 /// Database service for todo operations
 #[derive(Debug, Clone)]
 pub struct TodoDatabase {
@@ -11,18 +32,7 @@ pub struct TodoDatabase {
 impl TodoDatabase {
     /// Create a new database connection
     pub async fn new(database_url: &str) -> Result<Self> {
-        // Handle special cases for SQLite URL format
-        let url = match database_url {
-            ":memory:" => "sqlite::memory:".to_string(),
-            path if path.starts_with("sqlite://") => path.to_string(),
-            path => {
-                // Create parent directory if needed for file databases
-                if let Some(parent) = std::path::Path::new(path).parent() {
-                    std::fs::create_dir_all(parent)?;
-                }
-                format!("sqlite://{}?mode=rwc", path)
-            }
-        };
+        let url = sqlite_url(database_url)?;
 
         let pool = SqlitePoolOptions::new()
             .max_connections(5)
@@ -0,0 +1,390 @@
+use crate::database::sqlite_url;
+use crate::proxy::{Proxy, ProxyAuth, ProxyCheckResult, ProxyCheckStatus, ProxyType};
+use crate::Result;
+use chrono::{DateTime, SecondsFormat, Utc};
+use sqlx::{sqlite::SqlitePoolOptions, Row, SqlitePool};
+
+/// A stored proxy together with its latest check outcome
+#[derive(Debug, Clone)]
+pub struct ProxyRecord {
+    pub proxy: Proxy,
+    /// Whether the most recent check succeeded (`None` if never checked)
+    pub last_working: Option<bool>,
+    pub last_response_time_ms: Option<u64>,
+    pub last_checked_at: Option<DateTime<Utc>>,
+    /// When the proxy is next due for a recheck (`None` means now)
+    pub next_check_at: Option<DateTime<Utc>>,
+    /// User-assigned labels such as `flaky` or `premium`
+    pub tags: Vec<String>,
+}
+
+/// Database service for proxies and their check history
+#[derive(Debug, Clone)]
+pub struct ProxyDatabase {
+    pool: SqlitePool,
+}
+
+/// Format timestamps with a fixed width so they compare correctly as text
+fn timestamp(time: DateTime<Utc>) -> String {
+    time.to_rfc3339_opts(SecondsFormat::Millis, true)
+}
+
+/// Tags are stored as one comma-separated column
+fn join_tags(tags: &[String]) -> Option<String> {
+    let mut cleaned: Vec<&str> = Vec::new();
+    for tag in tags.iter().map(|t| t.trim()) {
+        if !tag.is_empty() && !cleaned.contains(&tag) {
+            cleaned.push(tag);
+        }
+    }
+    if cleaned.is_empty() {
+        None
+    } else {
+        Some(cleaned.join(","))
+    }
+}
+
+fn split_tags(value: Option<String>) -> Vec<String> {
+    value
+        .map(|v| v.split(',').map(str::to_string).collect())
+        .unwrap_or_default()
+}
+
+fn parse_timestamp(value: Option<String>) -> Option<DateTime<Utc>> {
+    value
+        .and_then(|v| DateTime::parse_from_rfc3339(&v).ok())
+        .map(|t| t.with_timezone(&Utc))
+}
+
+impl ProxyDatabase {
+    /// Create a new database connection
+    pub async fn new(database_url: &str) -> Result<Self> {
+        let url = sqlite_url(database_url)?;
+
+        // Every connection to an in-memory database is a separate database,
+        // so keep a single connection alive for its whole lifetime
+        let options = if database_url == ":memory:" {
+            SqlitePoolOptions::new()
+                .max_connections(1)
+                .min_connections(1)
+                .idle_timeout(None)
+                .max_lifetime(None)
+        } else {
+            SqlitePoolOptions::new().max_connections(5)
+        };
+        let pool = options.connect(&url).await?;
+
+        let db = Self { pool };
+        db.migrate().await?;
+        Ok(db)
+    }
+
+    /// Close every connection, waiting for in-progress queries to finish
+    pub async fn close(&self) {
+        self.pool.close().await;
+    }
+
+    /// Whether [`ProxyDatabase::close`] has been called
+    pub fn is_closed(&self) -> bool {
+        self.pool.is_closed()
+    }
+
+    /// Run database migrations
+    async fn migrate(&self) -> Result<()> {
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS proxies (
+                host TEXT NOT NULL,
+                port INTEGER NOT NULL,
+                proxy_type TEXT NOT NULL,
+                username TEXT,
+                password TEXT,
+                last_working BOOLEAN,
+                last_response_time_ms INTEGER,
+                last_checked_at TEXT,
+                next_check_at TEXT,
+                tags TEXT,
+                created_at TEXT NOT NULL,
+                PRIMARY KEY (host, port)
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        // Databases created before tags existed lack the column
+        let has_tags: bool = sqlx::query_scalar(
+            "SELECT COUNT(*) > 0 FROM pragma_table_info('proxies') WHERE name = 'tags'",
+        )
+        .fetch_one(&self.pool)
+        .await?;
+        if !has_tags {
+            sqlx::query("ALTER TABLE proxies ADD COLUMN tags TEXT")
+                .execute(&self.pool)
+                .await?;
+        }
+
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS proxy_checks (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                host TEXT NOT NULL,
+                port INTEGER NOT NULL,
+                working BOOLEAN NOT NULL,
+                response_time_ms INTEGER,
+                error TEXT,
+                checked_at TEXT NOT NULL
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        sqlx::query(
+            "CREATE INDEX IF NOT EXISTS idx_proxy_checks_endpoint ON proxy_checks (host, port, id)",
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Insert a proxy, or update its type and credentials if it already exists
+    ///
+    /// Tags are left untouched, so re-importing or rechecking keeps them.
+    pub async fn upsert_proxy(&self, proxy: &Proxy) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO proxies (host, port, proxy_type, username, password, created_at)
+            VALUES (?, ?, ?, ?, ?, ?)
+            ON CONFLICT (host, port) DO UPDATE SET
+                proxy_type = excluded.proxy_type,
+                username = excluded.username,
+                password = excluded.password
+            "#,
+        )
+        .bind(&proxy.host)
+        .bind(proxy.port)
+        .bind(proxy.proxy_type.to_string())
+        .bind(proxy.auth.as_ref().map(|a| &a.username))
+        .bind(proxy.auth.as_ref().map(|a| &a.password))
+        .bind(timestamp(Utc::now()))
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Store a check result: updates the proxy's latest status and appends to its history
+    ///
+    /// Judge errors are not recorded, as they say nothing about the proxy.
+    pub async fn record_result(&self, result: &ProxyCheckResult) -> Result<()> {
+        if result.is_judge_error() {
+            return Ok(());
+        }
+        let proxy = &result.proxy;
+        let now = timestamp(Utc::now());
+        let working = result.is_working();
+        let response_time = result.response_time_ms.map(|ms| ms as i64);
+        let error = match &result.status {
+            ProxyCheckStatus::Working => None,
+            ProxyCheckStatus::Failed(e) => Some(e.clone()),
+            ProxyCheckStatus::Timeout => Some("timeout".to_string()),
+            ProxyCheckStatus::JudgeError(e) => Some(e.clone()),
+        };
+
+        self.upsert_proxy(proxy).await?;
+
+        sqlx::query(
+            "UPDATE proxies SET last_working = ?, last_response_time_ms = ?, last_checked_at = ? WHERE host = ? AND port = ?",
+        )
+        .bind(working)
+        .bind(response_time)
+        .bind(&now)
+        .bind(&proxy.host)
+        .bind(proxy.port)
+        .execute(&self.pool)
+        .await?;
+
+        sqlx::query(
+            "INSERT INTO proxy_checks (host, port, working, response_time_ms, error, checked_at) VALUES (?, ?, ?, ?, ?, ?)",
+        )
+        .bind(&proxy.host)
+        .bind(proxy.port)
+        .bind(working)
+        .bind(response_time)
+        .bind(error)
+        .bind(&now)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Get all stored proxies
+    pub async fn get_all_proxies(&self) -> Result<Vec<ProxyRecord>> {
+        let rows = sqlx::query("SELECT * FROM proxies ORDER BY host, port")
+            .fetch_all(&self.pool)
+            .await?;
+        rows.iter().map(Self::record_from_row).collect()
+    }
+
+    /// Get stored proxies whose most recent check succeeded
+    pub async fn get_working_proxies(&self) -> Result<Vec<Proxy>> {
+        let rows = sqlx::query("SELECT * FROM proxies WHERE last_working = 1 ORDER BY host, port")
+            .fetch_all(&self.pool)
+            .await?;
+        rows.iter()
+            .map(|row| Ok(Self::record_from_row(row)?.proxy))
+            .collect()
+    }
+
+    /// Get proxies whose next check time has passed (or was never scheduled)
+    pub async fn get_due_proxies(&self, now: DateTime<Utc>) -> Result<Vec<ProxyRecord>> {
+        let rows = sqlx::query(
+            "SELECT * FROM proxies WHERE next_check_at IS NULL OR next_check_at <= ? ORDER BY next_check_at",
+        )
+        .bind(timestamp(now))
+        .fetch_all(&self.pool)
+        .await?;
+        rows.iter().map(Self::record_from_row).collect()
+    }
+
+    /// Set when a proxy should next be rechecked
+    pub async fn set_next_check(&self, proxy: &Proxy, at: DateTime<Utc>) -> Result<()> {
+        sqlx::query("UPDATE proxies SET next_check_at = ? WHERE host = ? AND port = ?")
+            .bind(timestamp(at))
+            .bind(&proxy.host)
+            .bind(proxy.port)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    /// Replace a proxy's tags, storing the proxy first if it's unknown
+    ///
+    /// Blank and repeated tags are dropped; an empty list clears the tags.
+    pub async fn set_tags(&self, proxy: &Proxy, tags: &[String]) -> Result<()> {
+        self.upsert_proxy(proxy).await?;
+        sqlx::query("UPDATE proxies SET tags = ? WHERE host = ? AND port = ?")
+            .bind(join_tags(tags))
+            .bind(&proxy.host)
+            .bind(proxy.port)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    /// Get a proxy's tags (empty if it has none or isn't stored)
+    pub async fn get_tags(&self, proxy: &Proxy) -> Result<Vec<String>> {
+        let tags: Option<Option<String>> =
+            sqlx::query_scalar("SELECT tags FROM proxies WHERE host = ? AND port = ?")
+                .bind(&proxy.host)
+                .bind(proxy.port)
+                .fetch_optional(&self.pool)
+                .await?;
+        Ok(split_tags(tags.flatten()))
+    }
+
+    /// Get the outcomes of the most recent checks of a proxy, newest first
+    pub async fn get_recent_history(&self, proxy: &Proxy, limit: usize) -> Result<Vec<bool>> {
+        let rows = sqlx::query(
+            "SELECT working FROM proxy_checks WHERE host = ? AND port = ? ORDER BY id DESC LIMIT ?",
+        )
+        .bind(&proxy.host)
+        .bind(proxy.port)
+        .bind(limit as i64)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.iter()
+            .map(|row| Ok(row.try_get::<bool, _>("working")?))
+            .collect()
+    }
+
+    /// Total number of checks recorded so far
+    pub async fn count_checks(&self) -> Result<u64> {
+        let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM proxy_checks")
+            .fetch_one(&self.pool)
+            .await?;
+        Ok(count as u64)
+    }
+
+    /// Get proxies whose last `min_attempts` checks all failed
+    ///
+    /// Proxies with fewer than `min_attempts` recorded checks are never
+    /// included, so new proxies aren't flagged on a single bad check.
+    pub async fn consistently_dead(&self, min_attempts: usize) -> Result<Vec<Proxy>> {
+        let rows = sqlx::query(
+            r#"
+            WITH recent AS (
+                SELECT host, port, working,
+                       ROW_NUMBER() OVER (PARTITION BY host, port ORDER BY id DESC) AS rn
+                FROM proxy_checks
+            ),
+            dead AS (
+                SELECT host, port FROM recent
+                WHERE rn <= ?
+                GROUP BY host, port
+                HAVING COUNT(*) >= ? AND MAX(working) = 0
+            )
+            SELECT p.* FROM proxies p
+            JOIN dead d ON p.host = d.host AND p.port = d.port
+            ORDER BY p.host, p.port
+            "#,
+        )
+        .bind(min_attempts as i64)
+        .bind(min_attempts.max(1) as i64)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.iter()
+            .map(|row| Ok(Self::record_from_row(row)?.proxy))
+            .collect()
+    }
+
+    /// Delete proxies and their check history, returning how many were removed
+    pub async fn delete_proxies(&self, proxies: &[Proxy]) -> Result<usize> {
+        let mut tx = self.pool.begin().await?;
+        let mut deleted = 0;
+        for proxy in proxies {
+            sqlx::query("DELETE FROM proxy_checks WHERE host = ? AND port = ?")
+                .bind(&proxy.host)
+                .bind(proxy.port)
+                .execute(&mut *tx)
+                .await?;
+            deleted += sqlx::query("DELETE FROM proxies WHERE host = ? AND port = ?")
+                .bind(&proxy.host)
+                .bind(proxy.port)
+                .execute(&mut *tx)
+                .await?
+                .rows_affected() as usize;
+        }
+        tx.commit().await?;
+        Ok(deleted)
+    }
+
+    fn record_from_row(row: &sqlx::sqlite::SqliteRow) -> Result<ProxyRecord> {
+        let proxy_type: String = row.try_get("proxy_type")?;
+        let username: Option<String> = row.try_get("username")?;
+        let password: Option<String> = row.try_get("password")?;
+
+        let proxy = Proxy {
+            host: row.try_get("host")?,
+            port: row.try_get("port")?,
+            proxy_type: proxy_type.parse::<ProxyType>().unwrap_or_default(),
+            auth: match (username, password) {
+                (Some(username), Some(password)) => Some(ProxyAuth::new(username, password)),
+                _ => None,
+            },
+        };
+
+        Ok(ProxyRecord {
+            proxy,
+            last_working: row.try_get("last_working")?,
+            last_response_time_ms: row
+                .try_get::<Option<i64>, _>("last_response_time_ms")?
+                .map(|ms| ms as u64),
+            last_checked_at: parse_timestamp(row.try_get("last_checked_at")?),
+            next_check_at: parse_timestamp(row.try_get("next_check_at")?),
+            tags: split_tags(row.try_get("tags")?),
+        })
+    }
+}
@@ -3,6 +3,7 @@
 //! This is a proxy parser and checker with multi-threading support.
 //! It can parse proxies from various formats and check their validity.
 
+pub mod daemon;
 pub mod database;
 pub mod models;
 pub mod proxy;
@@ -0,0 +1,154 @@
+//! Classifying proxy anonymity from what a judge saw
+
+use crate::proxy::models::AnonymityLevel;
+use std::net::{IpAddr, SocketAddr};
+
+/// Request headers carrying the client's address
+const CLIENT_IP_HEADERS: &[&str] = &[
+    "x-forwarded-for",
+    "x-real-ip",
+    "forwarded",
+    "client-ip",
+    "x-client-ip",
+];
+
+/// Request headers that only announce a proxy
+const PROXY_HEADERS: &[&str] = &[
+    "via",
+    "proxy-connection",
+    "proxy-agent",
+    "x-proxy-id",
+    "x-bluecoat-via",
+];
+
+/// Classify a proxy from a judge's echo of the request it received
+///
+/// Works with judges that echo headers as JSON (`"Via": ...`, httpbin's
+/// `/headers`), as `Name: value` lines, or as CGI variables (`HTTP_VIA`).
+/// A proxy is transparent when the judge saw `real_ip`; when the real IP is
+/// unknown, any client-address header counts as a leak.
+pub fn classify_anonymity(judge_body: &str, real_ip: Option<IpAddr>) -> AnonymityLevel {
+    let body = judge_body.to_ascii_lowercase().replace('_', "-");
+    let leaks_client_ip = match real_ip {
+        Some(ip) => ip_addresses(judge_body).any(|seen| seen == ip.to_canonical()),
+        None => CLIENT_IP_HEADERS.iter().any(|h| mentions_header(&body, h)),
+    };
+
+    if leaks_client_ip {
+        AnonymityLevel::Transparent
+    } else if CLIENT_IP_HEADERS
+        .iter()
+        .chain(PROXY_HEADERS)
+        .any(|h| mentions_header(&body, h))
+    {
+        AnonymityLevel::Anonymous
+    } else {
+        AnonymityLevel::Elite
+    }
+}
+
+/// IP addresses appearing in `body`, with IPv4-mapped IPv6 addresses as IPv4
+///
+/// Addresses are whole runs of hex digits, dots and colons, so `1.2.3.4` isn't
+/// found inside `11.2.3.45`; a trailing `:port` on IPv4 addresses is ignored.
+fn ip_addresses(body: &str) -> impl Iterator<Item = IpAddr> + '_ {
+    body.split(|c: char| !(c.is_ascii_hexdigit() || c == '.' || c == ':'))
+        .filter_map(|token| {
+            // Punctuation around an address (`ip:1.2.3.4.`) is tried without
+            let trimmed = token.trim_matches(|c| c == '.' || c == ':');
+            [token, trimmed].into_iter().find_map(|token| {
+                token
+                    .parse::<IpAddr>()
+                    .or_else(|_| token.parse::<SocketAddr>().map(|addr| addr.ip()))
+                    .ok()
+            })
+        })
+        .map(|ip| ip.to_canonical())
+}
+
+/// Whether the lowercased `body` names header `name` as a header, not just as a word
+fn mentions_header(body: &str, name: &str) -> bool {
+    body.contains(&format!("\"{}\"", name))
+        || body.contains(&format!("http-{}", name))
+        || body
+            .lines()
+            .any(|line| line.trim_start().starts_with(&format!("{}:", name)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classify_anonymity() {
+        let real_ip: IpAddr = "203.0.113.7".parse().unwrap();
+        let cases = [
+            (
+                r#"{"headers": {"Host": "judge.test"}}"#,
+                AnonymityLevel::Elite,
+            ),
+            (
+                r#"{"headers": {"Via": "1.1 squid", "Host": "judge.test"}}"#,
+                AnonymityLevel::Anonymous,
+            ),
+            (
+                "HTTP_X_FORWARDED_FOR = 198.51.100.1\nREMOTE_ADDR = 198.51.100.2",
+                AnonymityLevel::Anonymous,
+            ),
+            (
+                "Host: judge.test\nX-Forwarded-For: 203.0.113.7",
+                AnonymityLevel::Transparent,
+            ),
+            // "via" as a plain word is not a header
+            (
+                r#"{"origin": "198.51.100.2", "note": "served via cdn"}"#,
+                AnonymityLevel::Elite,
+            ),
+        ];
+
+        for (body, expected) in cases {
+            assert_eq!(
+                classify_anonymity(body, Some(real_ip)),
+                expected,
+                "{}",
+                body
+            );
+        }
+
+        // Only whole addresses count, in any of the usual notations
+        let real_ip: IpAddr = "1.2.3.4".parse().unwrap();
+        for (body, expected) in [
+            ("X-Forwarded-For: 11.2.3.45", AnonymityLevel::Anonymous),
+            ("X-Forwarded-For: 1.2.3.44", AnonymityLevel::Anonymous),
+            (
+                "X-Forwarded-For: 1.2.3.4, 198.51.100.1",
+                AnonymityLevel::Transparent,
+            ),
+            (r#"{"origin": "1.2.3.4"}"#, AnonymityLevel::Transparent),
+            ("Forwarded: for=1.2.3.4:5678", AnonymityLevel::Transparent),
+            ("X-Real-IP: ::ffff:1.2.3.4", AnonymityLevel::Transparent),
+        ] {
+            assert_eq!(
+                classify_anonymity(body, Some(real_ip)),
+                expected,
+                "{}",
+                body
+            );
+        }
+        let real_ip: IpAddr = "2001:db8::7".parse().unwrap();
+        assert_eq!(
+            classify_anonymity(r#"Forwarded: for="[2001:db8::7]:4711""#, Some(real_ip)),
+            AnonymityLevel::Transparent
+        );
+        assert_eq!(
+            classify_anonymity(r#"Forwarded: for="[2001:db8::77]""#, Some(real_ip)),
+            AnonymityLevel::Anonymous
+        );
+
+        // Without the real IP, forwarding headers are assumed to leak it
+        assert_eq!(
+            classify_anonymity(r#"{"X-Forwarded-For": "198.51.100.1"}"#, None),
+            AnonymityLevel::Transparent
+        );
+    }
+}
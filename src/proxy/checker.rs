@@ -1,14 +1,28 @@
 //! Proxy checker module for checking proxy validity
 
-use crate::proxy::models::{Proxy, ProxyCheckResult, ProxyType};
+use crate::proxy::anonymity::classify_anonymity;
+use crate::proxy::models::{AnonymityLevel, AnonymityVerdict, Proxy, ProxyCheckResult, ProxyType};
+use crate::proxy::probe;
+use crate::proxy::retry::RetryPolicy;
+use crate::proxy::sink::OutputSink;
 use crate::Result;
+use anyhow::anyhow;
+use base64::Engine;
+use futures::future::{self, BoxFuture};
 use futures::stream::{self, StreamExt};
-use reqwest::{Client, Proxy as ReqwestProxy};
+use reqwest::{Client, ClientBuilder, Proxy as ReqwestProxy, StatusCode};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::fmt::{self, Write as _};
+use std::hash::{Hash, Hasher};
+use std::net::{IpAddr, Ipv4Addr, SocketAddr, SocketAddrV4};
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 use tokio::sync::mpsc;
 use tokio::sync::Semaphore;
 
+pub use reqwest::tls::Version as TlsVersion;
+
 /// Default timeout for proxy checks in seconds
 const DEFAULT_TIMEOUT_SECS: u64 = 10;
 
@@ -18,8 +32,46 @@ const DEFAULT_CONCURRENCY: usize = 10;
 /// Default URL to test proxies against
 const DEFAULT_TEST_URL: &str = "http://httpbin.org/ip";
 
+/// Judge statuses meaning "try later" rather than "the proxy is broken"
+///
+/// Only 429: proxies produce 5xx pages themselves when they can't reach the
+/// judge (Squid answers a failed connect with 503), so those fail the proxy.
+pub const DEFAULT_JUDGE_ERROR_STATUSES: [u16; 1] = [429];
+
+/// Text found on common hotel, ISP and hotspot login pages
+///
+/// A proxy stuck behind such a portal answers with one of these pages (often
+/// with status 200) instead of the judge's response.
+pub const DEFAULT_CAPTIVE_PORTAL_SIGNATURES: &[&str] = &[
+    "captive portal",
+    "hotspot login",
+    "wifidog",
+    "coovachilli",
+    "chillispot",
+    "nomadix",
+    "mikrotik hotspot",
+    "web authentication redirect",
+    "log in to access the internet",
+    "login to access the internet",
+];
+
+/// Failure message for responses recognized as captive portal pages
+pub const CAPTIVE_PORTAL_ERROR: &str = "captive portal";
+
+/// Pause between the two requests of a keep-alive check
+const KEEPALIVE_CHECK_GAP: Duration = Duration::from_millis(500);
+
+/// Number of response body bytes shown by [`ProxyChecker::explain`]
+pub const EXPLAIN_BODY_BYTES: usize = 512;
+
+/// Share of the concurrency available immediately when ramping up
+const RAMP_INITIAL_DIVISOR: usize = 10;
+
+/// Hook adjusting the HTTP client built for each checked proxy
+pub type ClientCustomizer = Arc<dyn Fn(ClientBuilder) -> ClientBuilder + Send + Sync>;
+
 /// Configuration for proxy checker
-#[derive(Debug, Clone)]
+#[derive(Clone)]
 pub struct CheckerConfig {
     /// Timeout for each proxy check
     pub timeout: Duration,
@@ -27,6 +79,86 @@ pub struct CheckerConfig {
     pub concurrency: usize,
     /// URL to test proxies against
     pub test_url: String,
+    /// Judges used instead of `test_url` for particular proxy types
+    pub type_test_urls: HashMap<ProxyType, Vec<String>>,
+    /// Verify working proxies keep the connection open across two requests
+    pub keepalive_check: bool,
+    /// WebSocket endpoint (`ws://` or `wss://`) to attempt an upgrade against
+    pub websocket_url: Option<String>,
+    /// Echo endpoint a POST is sent to, to verify method and body survive the proxy
+    pub post_check_url: Option<String>,
+    /// Judge reachable only over IPv6, to verify the proxy's IPv6 egress
+    pub ipv6_judge_url: Option<String>,
+    /// Header-echo judges that each classify the proxy's anonymity
+    pub anonymity_judges: Vec<String>,
+    /// This machine's public IP, looked for in the judges' echo
+    pub real_ip: Option<IpAddr>,
+    /// Concurrency limits for particular proxy types, applied instead of
+    /// `concurrency` so slow types can't take every slot from the others
+    pub type_concurrency: HashMap<ProxyType, usize>,
+    /// Warmup period over which concurrency grows from a small value to `concurrency`
+    pub concurrency_ramp: Option<Duration>,
+    /// Check at most this many proxies at a time, pausing `batch_delay`
+    /// between batches (`None` checks everything as one batch)
+    pub batch_size: Option<usize>,
+    /// Pause between batches
+    pub batch_delay: Duration,
+    /// Probe working HTTP proxies for HTTPS (`CONNECT`) and SOCKS support
+    pub protocol_upgrade: bool,
+    /// Proxy the checker's own direct requests (such as the pre-flight) go
+    /// through, typically taken from `HTTP_PROXY`
+    pub direct_proxy: Option<String>,
+    /// Hosts reached without `direct_proxy`, using `NO_PROXY` syntax
+    pub no_proxy: Vec<String>,
+    /// Local IP address checks are sent from, for picking an egress interface
+    pub local_address: Option<IpAddr>,
+    /// Lowest TLS version accepted (the TLS backend's default when `None`)
+    pub min_tls_version: Option<TlsVersion>,
+    /// Highest TLS version offered (no maximum when `None`)
+    pub max_tls_version: Option<TlsVersion>,
+    /// Check `Https`-labelled proxies as `Http` (see [`CheckerConfig::with_normalize_type`])
+    pub normalize_type: bool,
+    /// Extra client options applied after the ones derived from this config
+    pub client_customizer: Option<ClientCustomizer>,
+    /// When to re-run a check that failed or timed out (no retries by default)
+    pub retry: RetryPolicy,
+    /// Judge response statuses blamed on the judge rather than the proxy
+    pub judge_error_statuses: Vec<u16>,
+    /// Case-insensitive texts marking a successful response as a captive
+    /// portal page (an empty list skips reading the body)
+    pub captive_portal_signatures: Vec<String>,
+}
+
+impl fmt::Debug for CheckerConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CheckerConfig")
+            .field("timeout", &self.timeout)
+            .field("concurrency", &self.concurrency)
+            .field("test_url", &self.test_url)
+            .field("type_test_urls", &self.type_test_urls)
+            .field("keepalive_check", &self.keepalive_check)
+            .field("websocket_url", &self.websocket_url)
+            .field("post_check_url", &self.post_check_url)
+            .field("ipv6_judge_url", &self.ipv6_judge_url)
+            .field("anonymity_judges", &self.anonymity_judges)
+            .field("real_ip", &self.real_ip)
+            .field("type_concurrency", &self.type_concurrency)
+            .field("concurrency_ramp", &self.concurrency_ramp)
+            .field("batch_size", &self.batch_size)
+            .field("batch_delay", &self.batch_delay)
+            .field("protocol_upgrade", &self.protocol_upgrade)
+            .field("direct_proxy", &self.direct_proxy)
+            .field("no_proxy", &self.no_proxy)
+            .field("local_address", &self.local_address)
+            .field("min_tls_version", &self.min_tls_version)
+            .field("max_tls_version", &self.max_tls_version)
+            .field("normalize_type", &self.normalize_type)
+            .field("client_customizer", &self.client_customizer.is_some())
+            .field("retry", &self.retry)
+            .field("judge_error_statuses", &self.judge_error_statuses)
+            .field("captive_portal_signatures", &self.captive_portal_signatures)
+            .finish()
+    }
 }
 
 impl Default for CheckerConfig {
@@ -35,6 +167,31 @@ impl Default for CheckerConfig {
             timeout: Duration::from_secs(DEFAULT_TIMEOUT_SECS),
             concurrency: DEFAULT_CONCURRENCY,
             test_url: DEFAULT_TEST_URL.to_string(),
+            type_test_urls: HashMap::new(),
+            keepalive_check: false,
+            websocket_url: None,
+            post_check_url: None,
+            ipv6_judge_url: None,
+            anonymity_judges: Vec::new(),
+            real_ip: None,
+            type_concurrency: HashMap::new(),
+            concurrency_ramp: None,
+            batch_size: None,
+            batch_delay: Duration::ZERO,
+            protocol_upgrade: false,
+            direct_proxy: None,
+            no_proxy: Vec::new(),
+            local_address: None,
+            min_tls_version: None,
+            max_tls_version: None,
+            normalize_type: false,
+            client_customizer: None,
+            retry: RetryPolicy::none(),
+            judge_error_statuses: DEFAULT_JUDGE_ERROR_STATUSES.to_vec(),
+            captive_portal_signatures: DEFAULT_CAPTIVE_PORTAL_SIGNATURES
+                .iter()
+                .map(|s| s.to_string())
+                .collect(),
         }
     }
 }
@@ -54,46 +211,623 @@ impl CheckerConfig {
         self
     }
 
+    /// Check proxies of `proxy_type` at most `concurrency` (at least 1) at a
+    /// time, separately from the limit shared by the other types
+    pub fn with_type_concurrency(mut self, proxy_type: ProxyType, concurrency: usize) -> Self {
+        self.type_concurrency.insert(proxy_type, concurrency.max(1));
+        self
+    }
+
     pub fn with_test_url(mut self, url: String) -> Self {
         self.test_url = url;
         self
     }
+
+    /// Check proxies of `proxy_type` against `urls` instead of the global test URL
+    ///
+    /// With several URLs, each proxy consistently uses one of them, so load is
+    /// spread across the judges.
+    pub fn with_type_test_urls(mut self, proxy_type: ProxyType, urls: Vec<String>) -> Self {
+        self.type_test_urls.insert(proxy_type, urls);
+        self
+    }
+
+    /// Judge URL a proxy is checked against
+    pub fn test_url_for(&self, proxy: &Proxy) -> &str {
+        match self.type_test_urls.get(&proxy.proxy_type) {
+            Some(urls) if !urls.is_empty() => {
+                let mut hasher = DefaultHasher::new();
+                (&proxy.host, proxy.port).hash(&mut hasher);
+                &urls[hasher.finish() as usize % urls.len()]
+            }
+            _ => &self.test_url,
+        }
+    }
+
+    /// Re-run failed checks according to `retry`, so one dropped packet
+    /// doesn't mark a proxy as bad
+    pub fn with_retry_policy(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// Treat these judge response statuses as judge errors instead of proxy
+    /// failures (an empty list blames every error status on the proxy)
+    pub fn with_judge_error_statuses(mut self, statuses: Vec<u16>) -> Self {
+        self.judge_error_statuses = statuses;
+        self
+    }
+
+    /// Fail responses containing any of `signatures` as captive portals
+    /// (an empty list disables the detection)
+    pub fn with_captive_portal_signatures(mut self, signatures: Vec<String>) -> Self {
+        self.captive_portal_signatures = signatures;
+        self
+    }
+
+    /// Whether a judge response body is a captive portal page
+    pub fn is_captive_portal(&self, body: &str) -> bool {
+        let body = body.to_lowercase();
+        self.captive_portal_signatures
+            .iter()
+            .any(|signature| body.contains(&signature.to_lowercase()))
+    }
+
+    pub fn with_keepalive_check(mut self, enabled: bool) -> Self {
+        self.keepalive_check = enabled;
+        self
+    }
+
+    /// Grow concurrency gradually up to `concurrency` over `ramp`
+    pub fn with_concurrency_ramp(mut self, ramp: Duration) -> Self {
+        self.concurrency_ramp = Some(ramp);
+        self
+    }
+
+    /// Check proxies `size` at a time (at least 1), sleeping `delay` between
+    /// batches; each batch still runs with the full concurrency
+    pub fn with_batch(mut self, size: usize, delay: Duration) -> Self {
+        self.batch_size = Some(size.max(1));
+        self.batch_delay = delay;
+        self
+    }
+
+    /// Split `proxies` into the batches they are checked in
+    fn batches(&self, proxies: Vec<Proxy>) -> Vec<Vec<Proxy>> {
+        match self.batch_size {
+            Some(size) if proxies.len() > size => {
+                proxies.chunks(size).map(<[Proxy]>::to_vec).collect()
+            }
+            _ => vec![proxies],
+        }
+    }
+
+    /// Attempt a WebSocket upgrade to `url` through each working proxy
+    pub fn with_websocket_check(mut self, url: String) -> Self {
+        self.websocket_url = Some(url);
+        self
+    }
+
+    /// POST a known body to the echo endpoint `url` through each working proxy
+    ///
+    /// The endpoint must echo the request back, either as httpbin-style JSON
+    /// (`{"method": ..., "data": ...}`, e.g. `/anything`) or as raw text.
+    pub fn with_post_check(mut self, url: String) -> Self {
+        self.post_check_url = Some(url);
+        self
+    }
+
+    /// Fetch the IPv6-only judge `url` through each working proxy
+    ///
+    /// Use a judge without an IPv4 address (e.g. an IPv6 literal such as
+    /// `http://[2001:db8::1]/ip`), so only proxies with IPv6 egress reach it.
+    pub fn with_ipv6_check(mut self, url: String) -> Self {
+        self.ipv6_judge_url = Some(url);
+        self
+    }
+
+    /// Classify working proxies' anonymity against every judge in `urls`
+    ///
+    /// Judges must echo the request headers they receive (like httpbin's
+    /// `/headers`). The result is the least anonymous level any judge saw,
+    /// so a proxy is only elite if every judge agrees; a judge that doesn't
+    /// answer keeps it from being elite.
+    pub fn with_anonymity_judges(mut self, urls: Vec<String>) -> Self {
+        self.anonymity_judges = urls;
+        self
+    }
+
+    /// Public IP of this machine; without it, any forwarding header is
+    /// treated as leaking the client's address
+    pub fn with_real_ip(mut self, ip: IpAddr) -> Self {
+        self.real_ip = Some(ip);
+        self
+    }
+
+    pub fn with_protocol_upgrade(mut self, enabled: bool) -> Self {
+        self.protocol_upgrade = enabled;
+        self
+    }
+
+    /// Send direct requests through `url` unless the target is bypassed
+    pub fn with_direct_proxy(mut self, url: String) -> Self {
+        self.direct_proxy = Some(url);
+        self
+    }
+
+    /// Hosts direct requests reach without the direct proxy
+    ///
+    /// Entries follow `NO_PROXY` conventions: `*` matches everything, a
+    /// domain matches itself and its subdomains, and IPs match exactly.
+    pub fn with_no_proxy(mut self, hosts: Vec<String>) -> Self {
+        self.no_proxy = hosts;
+        self
+    }
+
+    /// Send checks from `address` (one of this machine's IPs)
+    pub fn with_local_address(mut self, address: IpAddr) -> Self {
+        self.local_address = Some(address);
+        self
+    }
+
+    /// Refuse TLS versions older than `version`
+    ///
+    /// By default the TLS backend's own minimum applies. The default
+    /// (native-tls) backend can't use TLS 1.3 as a bound: checks then fail
+    /// with a client build error.
+    pub fn with_min_tls_version(mut self, version: TlsVersion) -> Self {
+        self.min_tls_version = Some(version);
+        self
+    }
+
+    /// Don't offer TLS versions newer than `version`
+    ///
+    /// By default there is no maximum; the same TLS 1.3 caveat as
+    /// [`CheckerConfig::with_min_tls_version`] applies.
+    pub fn with_max_tls_version(mut self, version: TlsVersion) -> Self {
+        self.max_tls_version = Some(version);
+        self
+    }
+
+    /// Check proxies labelled `Https` as plain `Http` proxies
+    ///
+    /// A stopgap until HTTPS proxies (TLS to the proxy itself) are supported:
+    /// both types are currently handled the same way, so users labelling HTTP
+    /// proxies as "https" get confusing results. Results keep the original
+    /// label.
+    pub fn with_normalize_type(mut self, enabled: bool) -> Self {
+        self.normalize_type = enabled;
+        self
+    }
+
+    /// Proxy type a proxy is checked as, after normalization
+    pub fn effective_type(&self, proxy_type: &ProxyType) -> ProxyType {
+        match proxy_type {
+            ProxyType::Https if self.normalize_type => ProxyType::Http,
+            other => other.clone(),
+        }
+    }
+
+    /// Adjust the client used for each checked proxy beyond what the config exposes
+    ///
+    /// The customizer runs last, right before the client is built: the proxy
+    /// and timeout from this config are already set, so it can override them
+    /// as well as set options such as `local_address` or `tcp_nodelay`.
+    pub fn with_client_customizer(mut self, customizer: ClientCustomizer) -> Self {
+        self.client_customizer = Some(customizer);
+        self
+    }
+
+    /// Take the direct proxy and bypass list from the environment
+    ///
+    /// Reads `HTTPS_PROXY`, `HTTP_PROXY` and `ALL_PROXY` (first set wins)
+    /// and the comma-separated `NO_PROXY`, in upper or lower case.
+    pub fn with_env_proxy(mut self) -> Self {
+        let var = |name: &str| {
+            std::env::var(name)
+                .or_else(|_| std::env::var(name.to_lowercase()))
+                .ok()
+                .filter(|value| !value.trim().is_empty())
+        };
+
+        if let Some(url) = ["HTTPS_PROXY", "HTTP_PROXY", "ALL_PROXY"]
+            .iter()
+            .find_map(|name| var(name))
+        {
+            self.direct_proxy = Some(url);
+        }
+        if let Some(no_proxy) = var("NO_PROXY") {
+            self.no_proxy = parse_no_proxy(&no_proxy);
+        }
+        self
+    }
+}
+
+/// Proxy URL with the credentials replaced by `***`, safe to print
+fn redacted_proxy_url(proxy: &Proxy) -> String {
+    let url = proxy.without_credentials().url();
+    match proxy.auth {
+        Some(_) => url.replacen("://", "://***@", 1),
+        None => url,
+    }
+}
+
+/// Render an error with all of its causes, e.g. `a: b: c`
+///
+/// Causes already included in the message (reqwest and hyper embed their
+/// sources) are not repeated.
+fn error_chain(error: &dyn std::error::Error) -> String {
+    let mut message = error.to_string();
+    let mut source = error.source();
+    while let Some(cause) = source {
+        let cause_message = cause.to_string();
+        if !message.contains(&cause_message) {
+            message.push_str(&format!(": {}", cause_message));
+        }
+        source = cause.source();
+    }
+    message
+}
+
+/// Split a comma-separated `NO_PROXY` value into entries
+pub fn parse_no_proxy(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(|entry| entry.trim().to_string())
+        .filter(|entry| !entry.is_empty())
+        .collect()
+}
+
+/// The least anonymous level any judge saw
+///
+/// A judge that didn't answer can't confirm the proxy is elite, so the
+/// result is capped at anonymous whenever a verdict is missing.
+fn overall_anonymity(verdicts: &[AnonymityVerdict]) -> Option<AnonymityLevel> {
+    let worst = verdicts.iter().filter_map(|verdict| verdict.level).min()?;
+    if verdicts.iter().any(|verdict| verdict.level.is_none()) {
+        return Some(worst.min(AnonymityLevel::Anonymous));
+    }
+    Some(worst)
+}
+
+/// Whether an echo judge's reply shows a POST of `body` arrived intact
+///
+/// httpbin-style JSON replies must report method `POST` and exactly `body`
+/// as data; any other reply only has to contain `body`.
+fn echo_matches(echo: &str, body: &str) -> bool {
+    match serde_json::from_str::<serde_json::Value>(echo) {
+        Ok(value) if value.get("method").is_some() || value.get("data").is_some() => {
+            let method_ok = value
+                .get("method")
+                .and_then(|m| m.as_str())
+                .is_none_or(|m| m.eq_ignore_ascii_case("POST"));
+            method_ok && value.get("data").and_then(|d| d.as_str()) == Some(body)
+        }
+        _ => echo.contains(body),
+    }
+}
+
+/// Whether `host` matches an entry of a `NO_PROXY`-style bypass list
+fn bypasses_proxy(host: &str, no_proxy: &[String]) -> bool {
+    let host = host
+        .trim_start_matches('[')
+        .trim_end_matches(']')
+        .to_ascii_lowercase();
+
+    no_proxy.iter().any(|entry| {
+        let entry = entry.to_ascii_lowercase();
+        if entry == "*" {
+            return true;
+        }
+        // Ports in entries are ignored; bracketed IPv6 entries keep their colons
+        let entry = match entry.strip_prefix('[') {
+            Some(rest) => rest.split(']').next().unwrap_or_default(),
+            None if entry.matches(':').count() == 1 => entry.split(':').next().unwrap_or_default(),
+            None => entry.as_str(),
+        };
+        let domain = entry.trim_start_matches('.');
+        !domain.is_empty() && (host == domain || host.ends_with(&format!(".{}", domain)))
+    })
+}
+
+/// Number of permits that should be available `elapsed` into a ramp-up
+///
+/// Starts at a tenth of `concurrency` (at least one) and grows linearly to
+/// the full `concurrency` at the end of the ramp.
+fn ramp_permits(concurrency: usize, ramp: Duration, elapsed: Duration) -> usize {
+    let concurrency = concurrency.max(1);
+    let initial = (concurrency / RAMP_INITIAL_DIVISOR).max(1);
+    if ramp.is_zero() || elapsed >= ramp {
+        return concurrency;
+    }
+
+    let progress = elapsed.as_secs_f64() / ramp.as_secs_f64();
+    initial + ((concurrency - initial) as f64 * progress) as usize
+}
+
+/// Semaphores limiting concurrent checks: one per type with its own limit
+/// (see [`CheckerConfig::with_type_concurrency`]) and one shared by the rest
+struct ConcurrencyLimits {
+    shared: (usize, Arc<Semaphore>),
+    per_type: HashMap<ProxyType, (usize, Arc<Semaphore>)>,
+}
+
+impl ConcurrencyLimits {
+    fn new(config: &CheckerConfig) -> Self {
+        let limit = |concurrency| {
+            (
+                concurrency,
+                concurrency_semaphore(concurrency, config.concurrency_ramp),
+            )
+        };
+        Self {
+            shared: limit(config.concurrency),
+            per_type: config
+                .type_concurrency
+                .iter()
+                .map(|(proxy_type, &concurrency)| (proxy_type.clone(), limit(concurrency)))
+                .collect(),
+        }
+    }
+
+    /// Split `proxies` into groups checked side by side, each with its
+    /// concurrency and semaphore
+    ///
+    /// Each group is driven separately, so queued proxies of one type never
+    /// hold up another type that still has free slots.
+    fn split(&self, proxies: Vec<Proxy>) -> Vec<(usize, Arc<Semaphore>, Vec<Proxy>)> {
+        let mut shared = Vec::new();
+        let mut per_type: HashMap<ProxyType, Vec<Proxy>> = HashMap::new();
+        for proxy in proxies {
+            if self.per_type.contains_key(&proxy.proxy_type) {
+                per_type
+                    .entry(proxy.proxy_type.clone())
+                    .or_default()
+                    .push(proxy);
+            } else {
+                shared.push(proxy);
+            }
+        }
+
+        let mut groups = Vec::with_capacity(per_type.len() + 1);
+        if !shared.is_empty() {
+            groups.push((self.shared.0, Arc::clone(&self.shared.1), shared));
+        }
+        for (proxy_type, proxies) in per_type {
+            let (concurrency, semaphore) = &self.per_type[&proxy_type];
+            groups.push((*concurrency, Arc::clone(semaphore), proxies));
+        }
+        groups
+    }
+}
+
+/// Create the semaphore limiting concurrent checks
+///
+/// Without a ramp all permits are available immediately. With a ramp the
+/// semaphore starts small and a background task adds permits over time.
+fn concurrency_semaphore(concurrency: usize, ramp: Option<Duration>) -> Arc<Semaphore> {
+    let Some(ramp) = ramp else {
+        return Arc::new(Semaphore::new(concurrency));
+    };
+
+    let initial = ramp_permits(concurrency, ramp, Duration::ZERO);
+    let semaphore = Arc::new(Semaphore::new(initial));
+    let steps = concurrency.saturating_sub(initial);
+    if steps == 0 {
+        return semaphore;
+    }
+
+    // Hold a weak reference so the ramp stops once the checks are done
+    let weak = Arc::downgrade(&semaphore);
+    let step = ramp / steps as u32;
+    tokio::spawn(async move {
+        let start = tokio::time::Instant::now();
+        let mut granted = initial;
+        while granted < concurrency {
+            tokio::time::sleep(step).await;
+            let Some(semaphore) = weak.upgrade() else {
+                return;
+            };
+            let target = ramp_permits(concurrency, ramp, start.elapsed());
+            if target > granted {
+                semaphore.add_permits(target - granted);
+                granted = target;
+            }
+        }
+    });
+
+    semaphore
+}
+
+/// A check of a single proxy
+///
+/// [`ProxyChecker`] runs its batch methods through this trait, so the network
+/// check can be swapped for a fake such as [`MockCheck`].
+pub trait ProxyCheck: Send + Sync {
+    fn check<'a>(&'a self, proxy: &'a Proxy) -> BoxFuture<'a, ProxyCheckResult>;
+}
+
+/// Deterministic check that never touches the network
+///
+/// A proxy works when the last octet of its IPv4 address is even (for other
+/// hosts, when the sum of the host's bytes is even). Response times are
+/// derived from the port so repeated runs produce identical results.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MockCheck;
+
+impl MockCheck {
+    fn is_working(proxy: &Proxy) -> bool {
+        let key = match proxy.host.parse::<Ipv4Addr>() {
+            Ok(ip) => ip.octets()[3] as u32,
+            Err(_) => proxy.host.bytes().map(u32::from).sum(),
+        };
+        key % 2 == 0
+    }
+}
+
+impl ProxyCheck for MockCheck {
+    fn check<'a>(&'a self, proxy: &'a Proxy) -> BoxFuture<'a, ProxyCheckResult> {
+        let result = if Self::is_working(proxy) {
+            ProxyCheckResult::working(proxy.clone(), 50 + u64::from(proxy.port % 100))
+        } else {
+            ProxyCheckResult::failed(proxy.clone(), "mock check: proxy marked as failed".into())
+        };
+        Box::pin(future::ready(result))
+    }
+}
+
+/// Running totals while a batch of proxies is checked
+///
+/// Judge errors count towards `checked` but neither `good` nor `bad`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CheckProgress {
+    pub checked: usize,
+    pub total: usize,
+    pub good: usize,
+    pub bad: usize,
+}
+
+impl CheckProgress {
+    /// Whether a periodic report is due: every `every` results, and once at the end
+    pub fn is_due(&self, every: usize) -> bool {
+        self.checked == self.total || (every > 0 && self.checked.is_multiple_of(every))
+    }
+}
+
+impl fmt::Display for CheckProgress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Checked {}/{}: {} good, {} bad",
+            self.checked, self.total, self.good, self.bad
+        )
+    }
 }
 
 /// Proxy checker for validating proxies
 pub struct ProxyChecker {
     config: CheckerConfig,
+    /// Replaces the network check when set
+    check: Option<Arc<dyn ProxyCheck>>,
 }
 
 impl ProxyChecker {
     /// Create a new proxy checker with default configuration
     pub fn new() -> Self {
-        Self {
-            config: CheckerConfig::default(),
-        }
+        Self::with_config(CheckerConfig::default())
     }
 
     /// Create a new proxy checker with custom configuration
     pub fn with_config(config: CheckerConfig) -> Self {
-        Self { config }
+        Self {
+            config,
+            check: None,
+        }
+    }
+
+    /// Route every check through `check` instead of the network
+    pub fn with_check(mut self, check: Arc<dyn ProxyCheck>) -> Self {
+        self.check = Some(check);
+        self
     }
 
     /// Check a single proxy
     pub async fn check_proxy(&self, proxy: &Proxy) -> ProxyCheckResult {
+        let effective_type = self.config.effective_type(&proxy.proxy_type);
+        if effective_type == proxy.proxy_type {
+            return self.run_check(proxy).await;
+        }
+
+        // Check under the normalized type but report the original label
+        let mut normalized = proxy.clone();
+        normalized.proxy_type = effective_type.clone();
+        let mut result = self.run_check(&normalized).await;
+        result.proxy = proxy.clone();
+        if result.verified_type.as_ref() == Some(&effective_type) {
+            result.verified_type = Some(proxy.proxy_type.clone());
+        }
+        result
+    }
+
+    /// Check once, retrying failures as the retry policy allows
+    async fn run_check(&self, proxy: &Proxy) -> ProxyCheckResult {
+        let retry = &self.config.retry;
+        let mut attempt = 1;
+        loop {
+            let result = match self.check {
+                Some(ref check) => check.check(proxy).await,
+                None => self.check_proxy_network(proxy).await,
+            };
+            if result.is_working() || !retry.should_retry(attempt) {
+                return result;
+            }
+            tokio::time::sleep(retry.next_delay(attempt)).await;
+            attempt += 1;
+        }
+    }
+
+    /// Check a single proxy by fetching the test URL through it
+    async fn check_proxy_network(&self, proxy: &Proxy) -> ProxyCheckResult {
         let start = Instant::now();
 
         match self.create_client(proxy) {
             Ok(client) => {
                 match tokio::time::timeout(
                     self.config.timeout,
-                    client.get(&self.config.test_url).send(),
+                    client.get(self.config.test_url_for(proxy)).send(),
                 )
                 .await
                 {
                     Ok(Ok(response)) => {
                         if response.status().is_success() {
                             let elapsed = start.elapsed().as_millis() as u64;
-                            ProxyCheckResult::working(proxy.clone(), elapsed)
+                            if !self.config.captive_portal_signatures.is_empty() {
+                                // A body that can't be read is no evidence of a portal
+                                let body =
+                                    tokio::time::timeout(self.config.timeout, response.text())
+                                        .await
+                                        .ok()
+                                        .and_then(|body| body.ok())
+                                        .unwrap_or_default();
+                                if self.config.is_captive_portal(&body) {
+                                    return ProxyCheckResult::failed(
+                                        proxy.clone(),
+                                        CAPTIVE_PORTAL_ERROR.to_string(),
+                                    );
+                                }
+                            }
+                            let mut result = ProxyCheckResult::working(proxy.clone(), elapsed);
+                            if self.config.keepalive_check {
+                                result.keepalive_ok = self.check_keepalive(proxy).await;
+                            }
+                            if let Some(ref url) = self.config.websocket_url {
+                                result.supports_websocket =
+                                    Some(self.check_websocket(&client, url).await);
+                            }
+                            if let Some(ref url) = self.config.post_check_url {
+                                result.preserves_post = Some(self.check_post(&client, url).await);
+                            }
+                            if let Some(ref url) = self.config.ipv6_judge_url {
+                                result.supports_ipv6_egress =
+                                    Some(self.check_ipv6_egress(&client, url).await);
+                            }
+                            if !self.config.anonymity_judges.is_empty() {
+                                result.judge_anonymity = self.check_anonymity(&client).await;
+                                result.anonymity = overall_anonymity(&result.judge_anonymity);
+                            }
+                            if self.config.protocol_upgrade && proxy.proxy_type == ProxyType::Http {
+                                result.capabilities = self.probe_capabilities(proxy).await;
+                            }
+                            result
+                        } else if self
+                            .config
+                            .judge_error_statuses
+                            .contains(&response.status().as_u16())
+                        {
+                            ProxyCheckResult::judge_error(
+                                proxy.clone(),
+                                format!("Judge returned HTTP status: {}", response.status()),
+                            )
                         } else {
                             ProxyCheckResult::failed(
                                 proxy.clone(),
@@ -109,25 +843,260 @@ impl ProxyChecker {
         }
     }
 
-    /// Check multiple proxies concurrently
-    pub async fn check_proxies(&self, proxies: Vec<Proxy>) -> Vec<ProxyCheckResult> {
-        let semaphore = Arc::new(Semaphore::new(self.config.concurrency));
-
-        let results = stream::iter(proxies)
-            .map(|proxy| {
-                let sem = Arc::clone(&semaphore);
-                let checker = self.clone();
-                async move {
-                    // Semaphore acquire only fails if the semaphore is closed,
-                    // which won't happen here since we own the Arc and keep it alive
-                    // for the duration of the check operation.
-                    let _permit = sem.acquire().await.expect("Semaphore closed unexpectedly");
-                    checker.check_proxy(&proxy).await
+    /// Run the check request for `proxy` and describe it in detail
+    ///
+    /// A debugging aid over [`ProxyChecker::check_proxy`]: reports the request
+    /// URL and headers, then the response status, headers and the first
+    /// [`EXPLAIN_BODY_BYTES`] bytes of the body, or the full error chain.
+    /// Proxy credentials are redacted.
+    pub async fn explain(&self, proxy: &Proxy) -> String {
+        let test_url = self.config.test_url_for(proxy);
+        let mut out = String::new();
+        let _ = writeln!(out, "Proxy: {}", redacted_proxy_url(proxy));
+
+        let client = match self.create_client(proxy) {
+            Ok(client) => client,
+            Err(e) => {
+                let _ = writeln!(out, "Error: failed to build client: {:#}", e);
+                return out;
+            }
+        };
+        let request = match client.get(test_url).build() {
+            Ok(request) => request,
+            Err(e) => {
+                let _ = writeln!(out, "Error: invalid request: {}", error_chain(&e));
+                return out;
+            }
+        };
+
+        let _ = writeln!(out, "> {} {}", request.method(), request.url());
+        for (name, value) in request.headers() {
+            let _ = writeln!(out, "> {}: {}", name, value.to_str().unwrap_or("<binary>"));
+        }
+        if proxy.auth.is_some() && proxy.proxy_type.is_http() {
+            let _ = writeln!(out, "> proxy-authorization: Basic [redacted]");
+        }
+
+        let start = Instant::now();
+        let response =
+            match tokio::time::timeout(self.config.timeout, client.execute(request)).await {
+                Ok(Ok(response)) => response,
+                Ok(Err(e)) => {
+                    let _ = writeln!(out, "Error: {}", error_chain(&e));
+                    return out;
                 }
-            })
-            .buffer_unordered(self.config.concurrency)
-            .collect::<Vec<_>>()
-            .await;
+                Err(_) => {
+                    let _ = writeln!(out, "Error: timed out after {:?}", self.config.timeout);
+                    return out;
+                }
+            };
+
+        let _ = writeln!(
+            out,
+            "< {:?} {} ({}ms)",
+            response.version(),
+            response.status(),
+            start.elapsed().as_millis()
+        );
+        for (name, value) in response.headers() {
+            let _ = writeln!(out, "< {}: {}", name, value.to_str().unwrap_or("<binary>"));
+        }
+        match response.bytes().await {
+            Ok(body) => {
+                let shown = &body[..body.len().min(EXPLAIN_BODY_BYTES)];
+                let _ = writeln!(out, "<");
+                let _ = writeln!(out, "{}", String::from_utf8_lossy(shown));
+                if body.len() > shown.len() {
+                    let _ = writeln!(out, "... ({} more bytes)", body.len() - shown.len());
+                }
+            }
+            Err(e) => {
+                let _ = writeln!(out, "Error reading body: {}", error_chain(&e));
+            }
+        }
+        out
+    }
+
+    /// Check that a proxy holds the connection open between two requests
+    ///
+    /// Only HTTP proxies forwarding plain-HTTP requests are checked; tunnels
+    /// (SOCKS, `https://` test URLs) yield `None`.
+    async fn check_keepalive(&self, proxy: &Proxy) -> Option<bool> {
+        tokio::time::timeout(
+            self.config.timeout + KEEPALIVE_CHECK_GAP,
+            probe::check_keepalive(proxy, self.config.test_url_for(proxy), KEEPALIVE_CHECK_GAP),
+        )
+        .await
+        .unwrap_or(Some(false))
+    }
+
+    /// Check that a WebSocket upgrade to `url` goes through the proxy
+    async fn check_websocket(&self, client: &Client, url: &str) -> bool {
+        let http_url = if let Some(rest) = url.strip_prefix("ws://") {
+            format!("http://{}", rest)
+        } else if let Some(rest) = url.strip_prefix("wss://") {
+            format!("https://{}", rest)
+        } else {
+            url.to_string()
+        };
+        let key = base64::engine::general_purpose::STANDARD.encode(uuid::Uuid::new_v4().as_bytes());
+
+        let request = client
+            .get(http_url)
+            .header("Connection", "Upgrade")
+            .header("Upgrade", "websocket")
+            .header("Sec-WebSocket-Version", "13")
+            .header("Sec-WebSocket-Key", key)
+            .send();
+
+        match tokio::time::timeout(self.config.timeout, request).await {
+            Ok(Ok(response)) => {
+                response.status() == StatusCode::SWITCHING_PROTOCOLS
+                    && response
+                        .headers()
+                        .get("upgrade")
+                        .and_then(|v| v.to_str().ok())
+                        .is_some_and(|v| v.eq_ignore_ascii_case("websocket"))
+            }
+            _ => false,
+        }
+    }
+
+    /// Check that the proxy can reach the IPv6-only judge `url`
+    async fn check_ipv6_egress(&self, client: &Client, url: &str) -> bool {
+        matches!(
+            tokio::time::timeout(self.config.timeout, client.get(url).send()).await,
+            Ok(Ok(response)) if response.status().is_success()
+        )
+    }
+
+    /// Ask every anonymity judge what it saw of the request
+    async fn check_anonymity(&self, client: &Client) -> Vec<AnonymityVerdict> {
+        let mut verdicts = Vec::with_capacity(self.config.anonymity_judges.len());
+        for judge in &self.config.anonymity_judges {
+            let body = match tokio::time::timeout(self.config.timeout, client.get(judge).send())
+                .await
+            {
+                Ok(Ok(response)) if response.status().is_success() => response.text().await.ok(),
+                _ => None,
+            };
+            verdicts.push(AnonymityVerdict {
+                judge: judge.clone(),
+                level: body.map(|body| classify_anonymity(&body, self.config.real_ip)),
+            });
+        }
+        verdicts
+    }
+
+    /// Check that a POST to the echo endpoint `url` arrives unchanged
+    async fn check_post(&self, client: &Client, url: &str) -> bool {
+        let body = format!("open-proxy-post-check-{}", uuid::Uuid::new_v4());
+        let request = client
+            .post(url)
+            .header("Content-Type", "text/plain")
+            .body(body.clone())
+            .send();
+
+        match tokio::time::timeout(self.config.timeout, async {
+            let response = request.await?;
+            if !response.status().is_success() {
+                return Ok(None);
+            }
+            response.text().await.map(Some)
+        })
+        .await
+        {
+            Ok(Ok(Some(echo))) => echo_matches(&echo, &body),
+            _ => false,
+        }
+    }
+
+    /// Probe a working HTTP proxy for the other protocols it speaks
+    ///
+    /// `Http` is always listed since the proxy just forwarded the test request.
+    /// HTTPS support means a `CONNECT` to the test host on port 443 was accepted;
+    /// SOCKS support is detected by handshaking on the proxy's own port.
+    async fn probe_capabilities(&self, proxy: &Proxy) -> Vec<ProxyType> {
+        let mut capabilities = vec![ProxyType::Http];
+        let Ok(target) = reqwest::Url::parse(self.config.test_url_for(proxy)) else {
+            return capabilities;
+        };
+        let Some(host) = target.host_str() else {
+            return capabilities;
+        };
+        let authority = format!("{}:443", host);
+        let port = target.port_or_known_default().unwrap_or(80);
+
+        let socks4 = async {
+            let resolved = match host.parse::<Ipv4Addr>() {
+                Ok(ip) => Some(SocketAddrV4::new(ip, port)),
+                Err(_) => tokio::net::lookup_host((host, port))
+                    .await
+                    .ok()
+                    .and_then(|mut addrs| {
+                        addrs.find_map(|addr| match addr {
+                            SocketAddr::V4(v4) => Some(v4),
+                            SocketAddr::V6(_) => None,
+                        })
+                    }),
+            };
+            match resolved {
+                Some(addr) => probe::check_socks4(proxy, addr).await,
+                None => false,
+            }
+        };
+
+        let timeout = self.config.timeout;
+        let (https, socks4, socks5) = tokio::join!(
+            tokio::time::timeout(timeout, probe::check_connect(proxy, &authority)),
+            tokio::time::timeout(timeout, socks4),
+            tokio::time::timeout(timeout, probe::check_socks5(proxy)),
+        );
+
+        for (supported, proxy_type) in [
+            (https, ProxyType::Https),
+            (socks4, ProxyType::Socks4),
+            (socks5, ProxyType::Socks5),
+        ] {
+            if supported.unwrap_or(false) {
+                capabilities.push(proxy_type);
+            }
+        }
+        capabilities
+    }
+
+    /// Check multiple proxies concurrently, batch by batch when
+    /// [`CheckerConfig::with_batch`] is set
+    pub async fn check_proxies(&self, proxies: Vec<Proxy>) -> Vec<ProxyCheckResult> {
+        let limits = ConcurrencyLimits::new(&self.config);
+        let mut results = Vec::with_capacity(proxies.len());
+
+        for (i, batch) in self.config.batches(proxies).into_iter().enumerate() {
+            if i > 0 {
+                tokio::time::sleep(self.config.batch_delay).await;
+            }
+            let groups = limits
+                .split(batch)
+                .into_iter()
+                .map(|(concurrency, semaphore, group)| {
+                    stream::iter(group)
+                        .map(move |proxy| {
+                            let sem = Arc::clone(&semaphore);
+                            let checker = self.clone();
+                            async move {
+                                // Semaphore acquire only fails if the semaphore is closed,
+                                // which won't happen here since we own the Arc and keep it alive
+                                // for the duration of the check operation.
+                                let _permit =
+                                    sem.acquire().await.expect("Semaphore closed unexpectedly");
+                                checker.check_proxy(&proxy).await
+                            }
+                        })
+                        .buffer_unordered(concurrency)
+                        .collect::<Vec<_>>()
+                });
+            results.extend(future::join_all(groups).await.into_iter().flatten());
+        }
 
         results
     }
@@ -144,37 +1113,143 @@ impl ProxyChecker {
         (good, bad)
     }
 
+    /// Check proxies, writing each result to `good` or `bad` as it completes
+    ///
+    /// Judge errors say nothing about the proxy, so they go to neither sink.
+    /// Both sinks are flushed at the end; all results are returned.
+    pub async fn check_to_sinks(
+        &self,
+        proxies: Vec<Proxy>,
+        good: &mut dyn OutputSink,
+        bad: &mut dyn OutputSink,
+    ) -> Result<Vec<ProxyCheckResult>> {
+        let mut rx = self.check_proxies_stream(proxies);
+        let mut results = Vec::new();
+        while let Some(result) = rx.recv().await {
+            if result.is_working() {
+                good.write_result(&result)?;
+            } else if !result.is_judge_error() {
+                bad.write_result(&result)?;
+            }
+            results.push(result);
+        }
+        good.flush()?;
+        bad.flush()?;
+        Ok(results)
+    }
+
+    /// Check proxies and separate into good and bad results, calling
+    /// `on_progress` with the running totals after each result
+    pub async fn check_with_progress<F>(
+        &self,
+        proxies: Vec<Proxy>,
+        mut on_progress: F,
+    ) -> (Vec<ProxyCheckResult>, Vec<ProxyCheckResult>)
+    where
+        F: FnMut(&CheckProgress),
+    {
+        let mut progress = CheckProgress {
+            total: proxies.len(),
+            ..CheckProgress::default()
+        };
+        let mut rx = self.check_proxies_stream(proxies);
+        let (mut good, mut bad) = (Vec::new(), Vec::new());
+
+        while let Some(result) = rx.recv().await {
+            progress.checked += 1;
+            if result.is_working() {
+                progress.good += 1;
+                good.push(result);
+            } else {
+                if !result.is_judge_error() {
+                    progress.bad += 1;
+                }
+                bad.push(result);
+            }
+            on_progress(&progress);
+        }
+
+        (good, bad)
+    }
+
     /// Check proxies with streaming results via channel
     /// Returns a receiver that yields each result as it completes
     pub fn check_proxies_stream(&self, proxies: Vec<Proxy>) -> mpsc::Receiver<ProxyCheckResult> {
         let (tx, rx) = mpsc::channel(100);
         let checker = self.clone();
-        let concurrency = self.config.concurrency;
+        let batch_delay = self.config.batch_delay;
+        let batches = self.config.batches(proxies);
 
         tokio::spawn(async move {
-            let semaphore = Arc::new(Semaphore::new(concurrency));
-
-            let futures = proxies.into_iter().map(|proxy| {
-                let sem = Arc::clone(&semaphore);
-                let checker = checker.clone();
-                let tx = tx.clone();
-                async move {
-                    let _permit = sem.acquire().await.expect("Semaphore closed unexpectedly");
-                    let result = checker.check_proxy(&proxy).await;
-                    // Ignore send errors - receiver may have been dropped
-                    let _ = tx.send(result).await;
-                }
-            });
+            let limits = ConcurrencyLimits::new(&checker.config);
 
-            stream::iter(futures)
-                .buffer_unordered(concurrency)
-                .collect::<Vec<_>>()
-                .await;
+            for (i, batch) in batches.into_iter().enumerate() {
+                if i > 0 {
+                    tokio::time::sleep(batch_delay).await;
+                }
+                let groups =
+                    limits
+                        .split(batch)
+                        .into_iter()
+                        .map(|(concurrency, semaphore, group)| {
+                            let (checker, tx) = (checker.clone(), tx.clone());
+                            let futures = group.into_iter().map(move |proxy| {
+                                let sem = Arc::clone(&semaphore);
+                                let checker = checker.clone();
+                                let tx = tx.clone();
+                                async move {
+                                    let _permit =
+                                        sem.acquire().await.expect("Semaphore closed unexpectedly");
+                                    let result = checker.check_proxy(&proxy).await;
+                                    // Ignore send errors - receiver may have been dropped
+                                    let _ = tx.send(result).await;
+                                }
+                            });
+                            stream::iter(futures)
+                                .buffer_unordered(concurrency)
+                                .collect::<Vec<_>>()
+                        });
+                future::join_all(groups).await;
+            }
         });
 
         rx
     }
 
+    /// Check that the test URL is reachable without any of the checked proxies
+    ///
+    /// Run before a batch so an unreachable judge isn't mistaken for every
+    /// proxy failing. Honors `direct_proxy` and the `no_proxy` bypass list.
+    pub async fn preflight(&self) -> Result<()> {
+        let client = self.direct_client(&self.config.test_url)?;
+        let response = client.get(&self.config.test_url).send().await?;
+        if !response.status().is_success() {
+            return Err(anyhow!(
+                "Test URL {} returned HTTP status: {}",
+                self.config.test_url,
+                response.status()
+            ));
+        }
+        Ok(())
+    }
+
+    /// Client for requests made by the checker itself rather than through a checked proxy
+    fn direct_client(&self, url: &str) -> Result<Client> {
+        let host = reqwest::Url::parse(url)?
+            .host_str()
+            .unwrap_or_default()
+            .to_string();
+
+        let builder = Client::builder().no_proxy().timeout(self.config.timeout);
+        let builder = match self.config.direct_proxy {
+            Some(ref direct) if !bypasses_proxy(&host, &self.config.no_proxy) => {
+                builder.proxy(ReqwestProxy::all(direct)?)
+            }
+            _ => builder,
+        };
+        Ok(builder.build()?)
+    }
+
     /// Create a reqwest client with the proxy
     fn create_client(&self, proxy: &Proxy) -> Result<Client> {
         let proxy_url = proxy.url();
@@ -184,12 +1259,21 @@ impl ProxyChecker {
             ProxyType::Socks4 | ProxyType::Socks5 => ReqwestProxy::all(&proxy_url)?,
         };
 
-        let client = Client::builder()
+        let mut builder = Client::builder()
             .proxy(reqwest_proxy)
             .timeout(self.config.timeout)
-            .build()?;
+            .local_address(self.config.local_address);
+        if let Some(version) = self.config.min_tls_version {
+            builder = builder.min_tls_version(version);
+        }
+        if let Some(version) = self.config.max_tls_version {
+            builder = builder.max_tls_version(version);
+        }
+        if let Some(ref customize) = self.config.client_customizer {
+            builder = customize(builder);
+        }
 
-        Ok(client)
+        Ok(builder.build()?)
     }
 }
 
@@ -197,10 +1281,17 @@ impl Clone for ProxyChecker {
     fn clone(&self) -> Self {
         Self {
             config: self.config.clone(),
+            check: self.check.clone(),
         }
     }
 }
 
+impl ProxyCheck for ProxyChecker {
+    fn check<'a>(&'a self, proxy: &'a Proxy) -> BoxFuture<'a, ProxyCheckResult> {
+        Box::pin(self.check_proxy(proxy))
+    }
+}
+
 impl Default for ProxyChecker {
     fn default() -> Self {
         Self::new()
@@ -210,6 +1301,9 @@ impl Default for ProxyChecker {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::proxy::models::ProxyCheckStatus;
+    use crate::proxy::report::CheckSummary;
+    use crate::proxy::test_support::{spawn_http_server, MockReply};
 
     #[test]
     fn test_checker_config_default() {
@@ -217,6 +1311,14 @@ mod tests {
         assert_eq!(config.timeout, Duration::from_secs(DEFAULT_TIMEOUT_SECS));
         assert_eq!(config.concurrency, DEFAULT_CONCURRENCY);
         assert_eq!(config.test_url, DEFAULT_TEST_URL);
+        assert!(!config.keepalive_check);
+        assert!(config.websocket_url.is_none());
+        assert!(config.post_check_url.is_none());
+        assert!(config.ipv6_judge_url.is_none());
+        assert!(config.concurrency_ramp.is_none());
+        assert_eq!(config.judge_error_statuses, DEFAULT_JUDGE_ERROR_STATUSES);
+        assert!(config.min_tls_version.is_none());
+        assert!(config.max_tls_version.is_none());
     }
 
     #[test]
@@ -231,6 +1333,994 @@ mod tests {
         assert_eq!(config.test_url, "http://example.com");
     }
 
+    #[test]
+    fn test_tls_version_bounds_reach_the_client() {
+        let config = CheckerConfig::new()
+            .with_min_tls_version(TlsVersion::TLS_1_2)
+            .with_max_tls_version(TlsVersion::TLS_1_2);
+        assert_eq!(config.min_tls_version, Some(TlsVersion::TLS_1_2));
+        assert_eq!(config.max_tls_version, Some(TlsVersion::TLS_1_2));
+        let proxy = Proxy::new("127.0.0.1".to_string(), 8080, ProxyType::Http);
+        assert!(ProxyChecker::with_config(config)
+            .create_client(&proxy)
+            .is_ok());
+
+        // native-tls rejects TLS 1.3 as a bound when the client is built,
+        // which shows the configured bounds are applied to the builder
+        for config in [
+            CheckerConfig::new().with_min_tls_version(TlsVersion::TLS_1_3),
+            CheckerConfig::new().with_max_tls_version(TlsVersion::TLS_1_3),
+        ] {
+            let error = ProxyChecker::with_config(config)
+                .create_client(&proxy)
+                .unwrap_err();
+            assert!(error.to_string().contains("TLS"), "{}", error);
+        }
+    }
+
+    #[test]
+    fn test_test_url_for_proxy_type() {
+        let config = CheckerConfig::new()
+            .with_test_url("http://global.test/ip".to_string())
+            .with_type_test_urls(
+                ProxyType::Socks5,
+                vec!["http://socks-judge.test/ip".to_string()],
+            )
+            .with_type_test_urls(ProxyType::Https, Vec::new());
+
+        let socks = Proxy::new("10.0.0.1".to_string(), 1080, ProxyType::Socks5);
+        let http = Proxy::new("10.0.0.1".to_string(), 8080, ProxyType::Http);
+        let https = Proxy::new("10.0.0.1".to_string(), 443, ProxyType::Https);
+        assert_eq!(config.test_url_for(&socks), "http://socks-judge.test/ip");
+        assert_eq!(config.test_url_for(&http), "http://global.test/ip");
+        // An empty list falls back to the global test URL
+        assert_eq!(config.test_url_for(&https), "http://global.test/ip");
+    }
+
+    #[test]
+    fn test_test_url_for_spreads_proxies_across_judges() {
+        let judges: Vec<_> = (0..3)
+            .map(|i| format!("http://judge{}.test/ip", i))
+            .collect();
+        let config = CheckerConfig::new().with_type_test_urls(ProxyType::Http, judges.clone());
+
+        let mut used = std::collections::HashSet::new();
+        for i in 0..50 {
+            let proxy = Proxy::new(format!("10.0.0.{}", i), 8080, ProxyType::Http);
+            let judge = config.test_url_for(&proxy);
+            // The same proxy always gets the same judge
+            assert_eq!(judge, config.test_url_for(&proxy.clone()));
+            assert!(judges.iter().any(|j| j == judge));
+            used.insert(judge.to_string());
+        }
+        assert_eq!(used.len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_check_uses_type_specific_judge() {
+        let addr = spawn_http_server(|req| {
+            if req.target.starts_with("http://http-judge.test/") {
+                MockReply::new(200, "ok")
+            } else {
+                MockReply::new(404, "wrong judge")
+            }
+        })
+        .await;
+        let checker = ProxyChecker::with_config(
+            CheckerConfig::new()
+                .with_test_url("http://global.test/ip".to_string())
+                .with_type_test_urls(
+                    ProxyType::Http,
+                    vec!["http://http-judge.test/ip".to_string()],
+                ),
+        );
+
+        assert!(checker
+            .check_proxy(&mock_proxy_for(addr))
+            .await
+            .is_working());
+    }
+
+    #[tokio::test]
+    async fn test_rate_limited_judge_is_not_blamed_on_proxy() {
+        let addr = spawn_http_server(|_| MockReply::new(429, "slow down")).await;
+        let checker = ProxyChecker::with_config(
+            CheckerConfig::new().with_test_url("http://judge.test/ip".to_string()),
+        );
+
+        let result = checker.check_proxy(&mock_proxy_for(addr)).await;
+        assert!(result.is_judge_error(), "{:?}", result.status);
+        assert!(!result.is_working());
+        assert_eq!(CheckSummary::from_results(&[result]).failed, 0);
+    }
+
+    #[tokio::test]
+    async fn test_gateway_errors_still_fail_the_proxy() {
+        // Squid answers a connect failure with 503
+        for status in [500, 502, 503, 504] {
+            let addr = spawn_http_server(move |_| MockReply::new(status, "proxy error")).await;
+            let checker = ProxyChecker::with_config(
+                CheckerConfig::new().with_test_url("http://judge.test/ip".to_string()),
+            );
+
+            let result = checker.check_proxy(&mock_proxy_for(addr)).await;
+            assert!(
+                matches!(result.status, ProxyCheckStatus::Failed(_)),
+                "{}: {:?}",
+                status,
+                result.status
+            );
+        }
+
+        // An empty list blames every error status on the proxy
+        let addr = spawn_http_server(|_| MockReply::new(429, "slow down")).await;
+        let checker = ProxyChecker::with_config(
+            CheckerConfig::new()
+                .with_test_url("http://judge.test/ip".to_string())
+                .with_judge_error_statuses(Vec::new()),
+        );
+        let result = checker.check_proxy(&mock_proxy_for(addr)).await;
+        assert!(matches!(result.status, ProxyCheckStatus::Failed(_)));
+    }
+
+    #[tokio::test]
+    async fn test_explain_reports_status_and_headers() {
+        let addr = spawn_http_server(|_| {
+            MockReply::new(403, "blocked by upstream").header("X-Judge", "mock")
+        })
+        .await;
+        let mut proxy = mock_proxy_for(addr);
+        proxy.auth = Some(crate::proxy::models::ProxyAuth::new(
+            "user".to_string(),
+            "secret".to_string(),
+        ));
+        let checker = ProxyChecker::with_config(
+            CheckerConfig::new().with_test_url("http://judge.test/ip".to_string()),
+        );
+
+        let explanation = checker.explain(&proxy).await;
+        assert!(
+            explanation.contains("> GET http://judge.test/ip"),
+            "{}",
+            explanation
+        );
+        assert!(explanation.contains("403 Forbidden"), "{}", explanation);
+        assert!(explanation.contains("x-judge: mock"), "{}", explanation);
+        assert!(
+            explanation.contains("blocked by upstream"),
+            "{}",
+            explanation
+        );
+        assert!(explanation.contains("://***@"), "{}", explanation);
+        assert!(!explanation.contains("user:"), "{}", explanation);
+        assert!(!explanation.contains("secret"), "{}", explanation);
+    }
+
+    #[test]
+    fn test_redacted_proxy_url_matches_proxy_url() {
+        let proxy = Proxy::with_auth(
+            "2001:db8::1".to_string(),
+            1080,
+            ProxyType::Socks5,
+            "user".to_string(),
+            "secret".to_string(),
+        );
+        assert_eq!(
+            redacted_proxy_url(&proxy),
+            "socks5://***@[2001:db8::1]:1080"
+        );
+        assert_eq!(
+            redacted_proxy_url(&proxy.without_credentials()),
+            proxy.without_credentials().url()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_explain_reports_error() {
+        // Bind and drop a listener so the port is very likely closed
+        let addr = {
+            let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+            listener.local_addr().unwrap()
+        };
+        let checker = ProxyChecker::with_config(
+            CheckerConfig::new().with_test_url("http://judge.test/ip".to_string()),
+        );
+
+        let explanation = checker.explain(&mock_proxy_for(addr)).await;
+        assert!(explanation.contains("Error: "), "{}", explanation);
+        assert!(
+            explanation.to_lowercase().contains("refused"),
+            "{}",
+            explanation
+        );
+    }
+
+    /// Records the type each proxy was checked as
+    #[derive(Default)]
+    struct RecordingCheck(std::sync::Mutex<Vec<ProxyType>>);
+
+    impl ProxyCheck for RecordingCheck {
+        fn check<'a>(&'a self, proxy: &'a Proxy) -> BoxFuture<'a, ProxyCheckResult> {
+            self.0.lock().unwrap().push(proxy.proxy_type.clone());
+            Box::pin(future::ready(ProxyCheckResult::working(proxy.clone(), 10)))
+        }
+    }
+
+    /// Fails the first `failures` checks, then reports the proxy as working
+    struct FlakyCheck {
+        failures: usize,
+        calls: std::sync::atomic::AtomicUsize,
+    }
+
+    impl ProxyCheck for FlakyCheck {
+        fn check<'a>(&'a self, proxy: &'a Proxy) -> BoxFuture<'a, ProxyCheckResult> {
+            let call = self.calls.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            let result = if call < self.failures {
+                ProxyCheckResult::timeout(proxy.clone())
+            } else {
+                ProxyCheckResult::working(proxy.clone(), 10)
+            };
+            Box::pin(future::ready(result))
+        }
+    }
+
+    fn flaky(failures: usize) -> Arc<FlakyCheck> {
+        Arc::new(FlakyCheck {
+            failures,
+            calls: Default::default(),
+        })
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_retry_policy_retries_failed_checks() {
+        let check = flaky(2);
+        let checker = ProxyChecker::with_config(
+            CheckerConfig::new()
+                .with_retry_policy(RetryPolicy::exponential(3, Duration::from_millis(500))),
+        )
+        .with_check(check.clone());
+        let proxy = Proxy::new("10.0.0.1".to_string(), 8080, ProxyType::Http);
+
+        let start = tokio::time::Instant::now();
+        assert!(checker.check_proxy(&proxy).await.is_working());
+        assert_eq!(check.calls.load(std::sync::atomic::Ordering::SeqCst), 3);
+        // 500ms after the first failure, 1s after the second
+        assert_eq!(start.elapsed(), Duration::from_millis(1500));
+    }
+
+    #[tokio::test]
+    async fn test_no_retries_by_default() {
+        let check = flaky(1);
+        let checker = ProxyChecker::new().with_check(check.clone());
+        let proxy = Proxy::new("10.0.0.1".to_string(), 8080, ProxyType::Http);
+
+        assert!(!checker.check_proxy(&proxy).await.is_working());
+        assert_eq!(check.calls.load(std::sync::atomic::Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_normalize_type_checks_https_as_http() {
+        let recorder = Arc::new(RecordingCheck::default());
+        let checker = ProxyChecker::with_config(CheckerConfig::new().with_normalize_type(true))
+            .with_check(recorder.clone());
+        let proxy = Proxy::new("10.0.0.1".to_string(), 443, ProxyType::Https);
+
+        let result = checker.check_proxy(&proxy).await;
+        assert_eq!(*recorder.0.lock().unwrap(), vec![ProxyType::Http]);
+        // The original label is kept in the output
+        assert_eq!(result.proxy.proxy_type, ProxyType::Https);
+        assert_eq!(result.verified_type, Some(ProxyType::Https));
+    }
+
+    #[tokio::test]
+    async fn test_without_normalize_type_https_is_kept() {
+        let recorder = Arc::new(RecordingCheck::default());
+        let checker = ProxyChecker::new().with_check(recorder.clone());
+        let socks = Proxy::new("10.0.0.1".to_string(), 1080, ProxyType::Socks5);
+        let https = Proxy::new("10.0.0.1".to_string(), 443, ProxyType::Https);
+
+        checker.check_proxy(&https).await;
+        checker.check_proxy(&socks).await;
+        assert_eq!(
+            *recorder.0.lock().unwrap(),
+            vec![ProxyType::Https, ProxyType::Socks5]
+        );
+    }
+
+    #[test]
+    fn test_ramp_permits() {
+        let ramp = Duration::from_secs(10);
+        assert_eq!(ramp_permits(100, ramp, Duration::ZERO), 10);
+        assert_eq!(ramp_permits(100, ramp, Duration::from_secs(5)), 55);
+        assert_eq!(ramp_permits(100, ramp, Duration::from_secs(10)), 100);
+        assert_eq!(ramp_permits(100, ramp, Duration::from_secs(20)), 100);
+        // Small concurrency still starts with one permit
+        assert_eq!(ramp_permits(5, ramp, Duration::ZERO), 1);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_concurrency_semaphore_ramps_up_over_warmup() {
+        let semaphore = concurrency_semaphore(100, Some(Duration::from_secs(10)));
+        assert_eq!(semaphore.available_permits(), 10);
+
+        tokio::time::sleep(Duration::from_secs(5)).await;
+        let midway = semaphore.available_permits();
+        assert!(midway > 10 && midway < 100, "midway permits: {}", midway);
+
+        tokio::time::sleep(Duration::from_secs(6)).await;
+        assert_eq!(semaphore.available_permits(), 100);
+    }
+
+    #[test]
+    fn test_concurrency_semaphore_without_ramp() {
+        let semaphore = concurrency_semaphore(20, None);
+        assert_eq!(semaphore.available_permits(), 20);
+    }
+
+    fn mock_proxy_for(addr: std::net::SocketAddr) -> Proxy {
+        Proxy::new(addr.ip().to_string(), addr.port(), ProxyType::Http)
+    }
+
+    #[tokio::test]
+    async fn test_captive_portal_page_fails_check() {
+        let portal = spawn_http_server(|_| {
+            MockReply::new(
+                200,
+                "<html><head><title>Hotspot Login</title></head>\
+                 <body>Please accept the terms to continue</body></html>",
+            )
+        })
+        .await;
+        let judge = spawn_http_server(|_| MockReply::new(200, r#"{"origin": "1.2.3.4"}"#)).await;
+        let config = CheckerConfig::new().with_test_url("http://judge.test/ip".to_string());
+
+        let checker = ProxyChecker::with_config(config.clone());
+        let result = checker.check_proxy(&mock_proxy_for(portal)).await;
+        assert!(
+            matches!(&result.status, ProxyCheckStatus::Failed(e) if e == CAPTIVE_PORTAL_ERROR),
+            "{:?}",
+            result.status
+        );
+        assert!(checker
+            .check_proxy(&mock_proxy_for(judge))
+            .await
+            .is_working());
+
+        // Custom signatures replace the defaults; an empty list turns detection off
+        let custom = ProxyChecker::with_config(
+            config
+                .clone()
+                .with_captive_portal_signatures(vec!["ORIGIN".to_string()]),
+        );
+        assert!(custom
+            .check_proxy(&mock_proxy_for(portal))
+            .await
+            .is_working());
+        assert!(!custom
+            .check_proxy(&mock_proxy_for(judge))
+            .await
+            .is_working());
+        let disabled = ProxyChecker::with_config(config.with_captive_portal_signatures(Vec::new()));
+        assert!(disabled
+            .check_proxy(&mock_proxy_for(portal))
+            .await
+            .is_working());
+    }
+
+    #[tokio::test]
+    async fn test_keepalive_check_detects_reused_connection() {
+        let addr = spawn_http_server(|_| MockReply::new(200, "ok")).await;
+        let checker = ProxyChecker::with_config(
+            CheckerConfig::new()
+                .with_test_url("http://judge.test/ip".to_string())
+                .with_keepalive_check(true),
+        );
+
+        let result = checker.check_proxy(&mock_proxy_for(addr)).await;
+        assert!(result.is_working());
+        assert_eq!(result.keepalive_ok, Some(true));
+    }
+
+    #[tokio::test]
+    async fn test_keepalive_check_detects_dropped_connection() {
+        // Closes each connection after one response without announcing it
+        let addr = spawn_http_server(|_| MockReply::new(200, "ok").close()).await;
+        let checker = ProxyChecker::with_config(
+            CheckerConfig::new()
+                .with_test_url("http://judge.test/ip".to_string())
+                .with_keepalive_check(true),
+        );
+
+        let result = checker.check_proxy(&mock_proxy_for(addr)).await;
+        assert!(result.is_working());
+        assert_eq!(result.keepalive_ok, Some(false));
+    }
+
+    #[tokio::test]
+    async fn test_keepalive_check_disabled_by_default() {
+        let addr = spawn_http_server(|_| MockReply::new(200, "ok")).await;
+        let checker = ProxyChecker::with_config(
+            CheckerConfig::new().with_test_url("http://judge.test/ip".to_string()),
+        );
+
+        let result = checker.check_proxy(&mock_proxy_for(addr)).await;
+        assert!(result.is_working());
+        assert_eq!(result.keepalive_ok, None);
+    }
+
+    /// Mock proxy+endpoint that accepts upgrades only when `accept` is set
+    async fn spawn_websocket_mock(accept: bool) -> std::net::SocketAddr {
+        spawn_http_server(move |req| {
+            let wants_upgrade = req
+                .header("Upgrade")
+                .is_some_and(|v| v.eq_ignore_ascii_case("websocket"));
+            if !wants_upgrade {
+                MockReply::new(200, "ok")
+            } else if accept {
+                MockReply::new(101, "")
+                    .header("Upgrade", "websocket")
+                    .header("Connection", "Upgrade")
+            } else {
+                MockReply::new(400, "upgrade refused")
+            }
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn test_websocket_check_accepted() {
+        let addr = spawn_websocket_mock(true).await;
+        let checker = ProxyChecker::with_config(
+            CheckerConfig::new()
+                .with_test_url("http://judge.test/ip".to_string())
+                .with_websocket_check("ws://echo.test/socket".to_string()),
+        );
+
+        let result = checker.check_proxy(&mock_proxy_for(addr)).await;
+        assert!(result.is_working());
+        assert_eq!(result.supports_websocket, Some(true));
+    }
+
+    #[tokio::test]
+    async fn test_websocket_check_rejected() {
+        let addr = spawn_websocket_mock(false).await;
+        let checker = ProxyChecker::with_config(
+            CheckerConfig::new()
+                .with_test_url("http://judge.test/ip".to_string())
+                .with_websocket_check("ws://echo.test/socket".to_string()),
+        );
+
+        let result = checker.check_proxy(&mock_proxy_for(addr)).await;
+        assert!(result.is_working());
+        assert_eq!(result.supports_websocket, Some(false));
+    }
+
+    /// Mock proxy+echo judge in httpbin `/anything` style, optionally
+    /// losing the request body on the way like a mangling proxy
+    async fn spawn_echo_mock(drop_body: bool) -> std::net::SocketAddr {
+        spawn_http_server(move |req| {
+            let data = if drop_body {
+                String::new()
+            } else {
+                String::from_utf8_lossy(&req.body).into_owned()
+            };
+            let echo = serde_json::json!({ "method": req.method, "data": data });
+            MockReply::new(200, echo.to_string())
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn test_post_check_body_preserved() {
+        let addr = spawn_echo_mock(false).await;
+        let checker = ProxyChecker::with_config(
+            CheckerConfig::new()
+                .with_test_url("http://judge.test/ip".to_string())
+                .with_post_check("http://echo.test/anything".to_string()),
+        );
+
+        let result = checker.check_proxy(&mock_proxy_for(addr)).await;
+        assert!(result.is_working());
+        assert_eq!(result.preserves_post, Some(true));
+    }
+
+    #[tokio::test]
+    async fn test_post_check_detects_dropped_body() {
+        let addr = spawn_echo_mock(true).await;
+        let checker = ProxyChecker::with_config(
+            CheckerConfig::new()
+                .with_test_url("http://judge.test/ip".to_string())
+                .with_post_check("http://echo.test/anything".to_string()),
+        );
+
+        let result = checker.check_proxy(&mock_proxy_for(addr)).await;
+        assert!(result.is_working());
+        assert_eq!(result.preserves_post, Some(false));
+    }
+
+    /// Mock proxy+judge whose upstream side only has IPv4 unless `ipv6` is set
+    ///
+    /// Requests for IPv6-literal targets fail with 502 like a proxy without
+    /// an IPv6 route would report.
+    async fn spawn_ipv6_mock(ipv6: bool) -> std::net::SocketAddr {
+        spawn_http_server(move |req| {
+            if req.target.contains("://[") && !ipv6 {
+                MockReply::new(502, "network unreachable")
+            } else {
+                MockReply::new(200, "ok")
+            }
+        })
+        .await
+    }
+
+    fn ipv6_checker() -> ProxyChecker {
+        ProxyChecker::with_config(
+            CheckerConfig::new()
+                .with_test_url("http://judge.test/ip".to_string())
+                .with_ipv6_check("http://[2001:db8::1]/ip".to_string()),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_ipv6_check_reaches_judge() {
+        let addr = spawn_ipv6_mock(true).await;
+        let result = ipv6_checker().check_proxy(&mock_proxy_for(addr)).await;
+        assert!(result.is_working());
+        assert_eq!(result.supports_ipv6_egress, Some(true));
+    }
+
+    #[tokio::test]
+    async fn test_ipv6_check_without_ipv6_route() {
+        let addr = spawn_ipv6_mock(false).await;
+        let result = ipv6_checker().check_proxy(&mock_proxy_for(addr)).await;
+        assert!(result.is_working());
+        assert_eq!(result.supports_ipv6_egress, Some(false));
+    }
+
+    #[tokio::test]
+    async fn test_ipv6_check_skipped_for_failed_proxies() {
+        let addr = spawn_http_server(|_| MockReply::new(503, "down")).await;
+        let result = ipv6_checker().check_proxy(&mock_proxy_for(addr)).await;
+        assert!(!result.is_working());
+        assert_eq!(result.supports_ipv6_egress, None);
+    }
+
+    #[tokio::test]
+    async fn test_anonymity_is_the_worst_across_judges() {
+        let addr = spawn_http_server(|req| match req.target.as_str() {
+            "http://strict.test/headers" => {
+                MockReply::new(200, r#"{"headers": {"Via": "1.1 mock"}}"#)
+            }
+            "http://down.test/headers" => MockReply::new(503, "down"),
+            _ => MockReply::new(200, r#"{"headers": {"Host": "lenient.test"}}"#),
+        })
+        .await;
+        let judges = vec![
+            "http://lenient.test/headers".to_string(),
+            "http://strict.test/headers".to_string(),
+            "http://down.test/headers".to_string(),
+        ];
+        let checker = ProxyChecker::with_config(
+            CheckerConfig::new()
+                .with_test_url("http://judge.test/ip".to_string())
+                .with_anonymity_judges(judges.clone()),
+        );
+
+        let result = checker.check_proxy(&mock_proxy_for(addr)).await;
+        assert_eq!(result.anonymity, Some(AnonymityLevel::Anonymous));
+        let levels: Vec<_> = result
+            .judge_anonymity
+            .iter()
+            .map(|v| (v.judge.as_str(), v.level))
+            .collect();
+        assert_eq!(
+            levels,
+            vec![
+                (judges[0].as_str(), Some(AnonymityLevel::Elite)),
+                (judges[1].as_str(), Some(AnonymityLevel::Anonymous)),
+                (judges[2].as_str(), None),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_anonymity_is_not_elite_when_a_judge_is_down() {
+        let addr = spawn_http_server(|req| match req.target.as_str() {
+            "http://down.test/headers" => MockReply::new(503, "down"),
+            _ => MockReply::new(200, r#"{"headers": {"Host": "lenient.test"}}"#),
+        })
+        .await;
+        let checker = ProxyChecker::with_config(
+            CheckerConfig::new()
+                .with_test_url("http://judge.test/ip".to_string())
+                .with_anonymity_judges(vec![
+                    "http://lenient.test/headers".to_string(),
+                    "http://down.test/headers".to_string(),
+                ]),
+        );
+
+        let result = checker.check_proxy(&mock_proxy_for(addr)).await;
+        assert_eq!(result.judge_anonymity[0].level, Some(AnonymityLevel::Elite));
+        assert_eq!(result.judge_anonymity[1].level, None);
+        assert_eq!(result.anonymity, Some(AnonymityLevel::Anonymous));
+    }
+
+    #[test]
+    fn test_echo_matches() {
+        let body = "open-proxy-post-check-1";
+        assert!(echo_matches(
+            r#"{"method":"POST","data":"open-proxy-post-check-1"}"#,
+            body
+        ));
+        assert!(!echo_matches(
+            r#"{"method":"GET","data":"open-proxy-post-check-1"}"#,
+            body
+        ));
+        assert!(!echo_matches(r#"{"method":"POST","data":""}"#, body));
+        assert!(echo_matches("raw echo: open-proxy-post-check-1", body));
+        assert!(!echo_matches("raw echo:", body));
+    }
+
+    /// Mock HTTP proxy that tunnels `CONNECT` only when `allow_connect` is set
+    async fn spawn_connect_mock(allow_connect: bool) -> std::net::SocketAddr {
+        spawn_http_server(move |req| match req.method.as_str() {
+            "CONNECT" if allow_connect => MockReply::new(200, ""),
+            "CONNECT" => MockReply::new(405, "CONNECT not allowed"),
+            _ => MockReply::new(200, "ok"),
+        })
+        .await
+    }
+
+    fn protocol_upgrade_checker() -> ProxyChecker {
+        ProxyChecker::with_config(
+            CheckerConfig::new()
+                .with_test_url("http://judge.test/ip".to_string())
+                .with_timeout(Duration::from_secs(1))
+                .with_protocol_upgrade(true),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_protocol_upgrade_detects_connect() {
+        let addr = spawn_connect_mock(true).await;
+        let result = protocol_upgrade_checker()
+            .check_proxy(&mock_proxy_for(addr))
+            .await;
+
+        assert!(result.is_working());
+        assert_eq!(result.capabilities, vec![ProxyType::Http, ProxyType::Https]);
+    }
+
+    #[tokio::test]
+    async fn test_protocol_upgrade_without_connect() {
+        let addr = spawn_connect_mock(false).await;
+        let result = protocol_upgrade_checker()
+            .check_proxy(&mock_proxy_for(addr))
+            .await;
+
+        assert!(result.is_working());
+        assert_eq!(result.capabilities, vec![ProxyType::Http]);
+    }
+
+    #[tokio::test]
+    async fn test_protocol_upgrade_disabled_by_default() {
+        let addr = spawn_connect_mock(true).await;
+        let checker = ProxyChecker::with_config(
+            CheckerConfig::new().with_test_url("http://judge.test/ip".to_string()),
+        );
+
+        let result = checker.check_proxy(&mock_proxy_for(addr)).await;
+        assert!(result.capabilities.is_empty());
+    }
+
+    #[test]
+    fn test_bypasses_proxy() {
+        let no_proxy = parse_no_proxy("localhost, .corp.example,10.0.0.5:8080,[::1]");
+        assert!(bypasses_proxy("localhost", &no_proxy));
+        assert!(bypasses_proxy("judge.corp.example", &no_proxy));
+        assert!(bypasses_proxy("corp.example", &no_proxy));
+        assert!(bypasses_proxy("10.0.0.5", &no_proxy));
+        assert!(bypasses_proxy("[::1]", &no_proxy));
+        assert!(!bypasses_proxy("notcorp.example", &no_proxy));
+        assert!(!bypasses_proxy("httpbin.org", &no_proxy));
+        assert!(bypasses_proxy("anything", &["*".to_string()]));
+    }
+
+    #[tokio::test]
+    async fn test_preflight_honors_no_proxy() {
+        let via_proxy = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let counter = Arc::clone(&via_proxy);
+        let env_proxy = spawn_http_server(move |_| {
+            counter.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            MockReply::new(200, "via env proxy")
+        })
+        .await;
+        let direct_hits = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let counter = Arc::clone(&direct_hits);
+        let judge = spawn_http_server(move |_| {
+            counter.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            MockReply::new(200, "direct")
+        })
+        .await;
+
+        let config = CheckerConfig::new()
+            .with_direct_proxy(format!("http://{}", env_proxy))
+            .with_no_proxy(vec!["127.0.0.1".to_string()]);
+
+        // The bypassed judge is reached directly
+        let checker =
+            ProxyChecker::with_config(config.clone().with_test_url(format!("http://{}/ip", judge)));
+        checker.preflight().await.unwrap();
+        assert_eq!(direct_hits.load(std::sync::atomic::Ordering::SeqCst), 1);
+        assert_eq!(via_proxy.load(std::sync::atomic::Ordering::SeqCst), 0);
+
+        // Other hosts go through the env proxy
+        let checker =
+            ProxyChecker::with_config(config.with_test_url("http://judge.test/ip".to_string()));
+        checker.preflight().await.unwrap();
+        assert_eq!(direct_hits.load(std::sync::atomic::Ordering::SeqCst), 1);
+        assert_eq!(via_proxy.load(std::sync::atomic::Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_client_customizer_sets_local_address() {
+        let peers = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let seen = Arc::clone(&peers);
+        let addr = spawn_http_server(move |req| {
+            seen.lock().unwrap().push(req.peer.ip());
+            MockReply::new(200, "ok")
+        })
+        .await;
+
+        let local: IpAddr = "127.0.0.2".parse().unwrap();
+        let checker = ProxyChecker::with_config(
+            CheckerConfig::new()
+                .with_test_url("http://judge.test/ip".to_string())
+                .with_client_customizer(Arc::new(move |builder| builder.local_address(local))),
+        );
+
+        let result = checker.check_proxy(&mock_proxy_for(addr)).await;
+        assert!(result.is_working());
+        assert_eq!(*peers.lock().unwrap(), vec![local]);
+    }
+
+    #[tokio::test]
+    async fn test_local_address_is_used_for_checks() {
+        let peers = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let seen = Arc::clone(&peers);
+        let addr = spawn_http_server(move |req| {
+            seen.lock().unwrap().push(req.peer.ip());
+            MockReply::new(200, "ok")
+        })
+        .await;
+
+        let local: IpAddr = "127.0.0.3".parse().unwrap();
+        let checker = ProxyChecker::with_config(
+            CheckerConfig::new()
+                .with_test_url("http://judge.test/ip".to_string())
+                .with_local_address(local),
+        );
+
+        assert!(checker
+            .check_proxy(&mock_proxy_for(addr))
+            .await
+            .is_working());
+        assert_eq!(*peers.lock().unwrap(), vec![local]);
+    }
+
+    #[tokio::test]
+    async fn test_mock_check_is_deterministic() {
+        let checker = ProxyChecker::new().with_check(Arc::new(MockCheck));
+        let proxies = vec![
+            Proxy::new("10.0.0.2".to_string(), 8080, ProxyType::Http),
+            Proxy::new("10.0.0.3".to_string(), 8080, ProxyType::Http),
+            Proxy::new("10.0.0.4".to_string(), 3128, ProxyType::Socks5),
+        ];
+
+        let (good, bad) = checker.check_and_separate(proxies.clone()).await;
+        assert_eq!(good.len(), 2);
+        assert_eq!(bad.len(), 1);
+        assert_eq!(bad[0].proxy.host, "10.0.0.3");
+
+        let again = checker.check_proxy(&proxies[2]).await;
+        assert_eq!(again.response_time_ms, Some(78));
+    }
+
+    #[derive(Default)]
+    struct MemorySink {
+        entries: Vec<String>,
+        flushes: usize,
+    }
+
+    impl OutputSink for MemorySink {
+        fn write_proxy(&mut self, proxy: &Proxy) -> Result<()> {
+            self.entries.push(proxy.url());
+            Ok(())
+        }
+
+        fn flush(&mut self) -> Result<()> {
+            self.flushes += 1;
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_check_to_sinks_splits_results() {
+        let checker = ProxyChecker::new().with_check(Arc::new(MockCheck));
+        let proxies = vec![
+            Proxy::new("10.0.0.2".to_string(), 8080, ProxyType::Http),
+            Proxy::new("10.0.0.3".to_string(), 8080, ProxyType::Http),
+            Proxy::new("10.0.0.4".to_string(), 3128, ProxyType::Socks5),
+        ];
+
+        let mut good = MemorySink::default();
+        let mut bad = MemorySink::default();
+        let results = checker
+            .check_to_sinks(proxies, &mut good, &mut bad)
+            .await
+            .unwrap();
+
+        assert_eq!(results.len(), 3);
+        good.entries.sort();
+        assert_eq!(
+            good.entries,
+            vec!["http://10.0.0.2:8080", "socks5://10.0.0.4:3128"]
+        );
+        assert_eq!(bad.entries, vec!["http://10.0.0.3:8080"]);
+        assert_eq!((good.flushes, bad.flushes), (1, 1));
+    }
+
+    #[tokio::test]
+    async fn test_check_with_progress_reports_every_n() {
+        let checker = ProxyChecker::new().with_check(Arc::new(MockCheck));
+        let proxies: Vec<_> = (1..=10)
+            .map(|i| Proxy::new(format!("10.0.0.{}", i), 8080, ProxyType::Http))
+            .collect();
+
+        let mut lines = Vec::new();
+        let (good, bad) = checker
+            .check_with_progress(proxies, |progress| {
+                if progress.is_due(3) {
+                    lines.push(progress.to_string());
+                }
+            })
+            .await;
+
+        assert_eq!((good.len(), bad.len()), (5, 5));
+        // After 3, 6 and 9 results, then once more at the end
+        assert_eq!(lines.len(), 4);
+        assert!(lines[0].starts_with("Checked 3/10: "), "{:?}", lines);
+        assert_eq!(lines[3], "Checked 10/10: 5 good, 5 bad");
+    }
+
+    /// Records when each check starts; every check takes 20ms
+    #[derive(Default)]
+    struct TimedCheck(std::sync::Mutex<Vec<tokio::time::Instant>>);
+
+    impl ProxyCheck for TimedCheck {
+        fn check<'a>(&'a self, proxy: &'a Proxy) -> BoxFuture<'a, ProxyCheckResult> {
+            self.0.lock().unwrap().push(tokio::time::Instant::now());
+            Box::pin(async move {
+                tokio::time::sleep(Duration::from_millis(20)).await;
+                ProxyCheckResult::working(proxy.clone(), 20)
+            })
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_batches_are_separated_by_delay() {
+        let delay = Duration::from_millis(150);
+        let check = Arc::new(TimedCheck::default());
+        let checker = ProxyChecker::with_config(
+            CheckerConfig::new()
+                .with_concurrency(10)
+                .with_batch(2, delay),
+        )
+        .with_check(check.clone());
+        let proxies: Vec<_> = (1..=5)
+            .map(|i| Proxy::new(format!("10.0.0.{}", i), 8080, ProxyType::Http))
+            .collect();
+
+        for streamed in [false, true] {
+            check.0.lock().unwrap().clear();
+            let results = if streamed {
+                checker.check_with_progress(proxies.clone(), |_| {}).await.0
+            } else {
+                checker.check_proxies(proxies.clone()).await
+            };
+            assert_eq!(results.len(), 5);
+
+            // A batch starts together, `delay` after the previous one's 20ms checks
+            let mut starts = check.0.lock().unwrap().clone();
+            starts.sort();
+            let offsets: Vec<_> = starts.iter().map(|&start| start - starts[0]).collect();
+            let next = delay + Duration::from_millis(20);
+            assert_eq!(
+                offsets,
+                vec![Duration::ZERO, Duration::ZERO, next, next, next * 2],
+                "streamed: {}",
+                streamed
+            );
+        }
+    }
+
+    /// Records how many checks of each type run at once, and whether HTTP
+    /// checks ran while SOCKS checks were in flight
+    #[derive(Default)]
+    struct InFlightCheck(std::sync::Mutex<InFlight>);
+
+    #[derive(Default)]
+    struct InFlight {
+        running: HashMap<ProxyType, usize>,
+        peak: HashMap<ProxyType, usize>,
+        overlapped: bool,
+    }
+
+    impl ProxyCheck for InFlightCheck {
+        fn check<'a>(&'a self, proxy: &'a Proxy) -> BoxFuture<'a, ProxyCheckResult> {
+            Box::pin(async move {
+                {
+                    let mut state = self.0.lock().unwrap();
+                    let running = state.running.entry(proxy.proxy_type.clone()).or_default();
+                    *running += 1;
+                    let running = *running;
+                    let peak = state.peak.entry(proxy.proxy_type.clone()).or_default();
+                    *peak = (*peak).max(running);
+                    if state.running.get(&ProxyType::Http).copied().unwrap_or(0) > 0
+                        && state.running.get(&ProxyType::Socks5).copied().unwrap_or(0) > 0
+                    {
+                        state.overlapped = true;
+                    }
+                }
+                // SOCKS checks are the slow ones
+                let millis = if proxy.proxy_type == ProxyType::Socks5 {
+                    40
+                } else {
+                    5
+                };
+                tokio::time::sleep(Duration::from_millis(millis)).await;
+                *self
+                    .0
+                    .lock()
+                    .unwrap()
+                    .running
+                    .get_mut(&proxy.proxy_type)
+                    .unwrap() -= 1;
+                ProxyCheckResult::working(proxy.clone(), millis)
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_type_concurrency_keeps_socks_from_starving_http() {
+        // Slow SOCKS proxies first, so a shared limit would be taken by them
+        let proxies: Vec<_> = (1..=12)
+            .map(|i| Proxy::new(format!("10.0.1.{}", i), 1080, ProxyType::Socks5))
+            .chain((1..=6).map(|i| Proxy::new(format!("10.0.2.{}", i), 8080, ProxyType::Http)))
+            .collect();
+
+        for streamed in [false, true] {
+            let check = Arc::new(InFlightCheck::default());
+            let checker = ProxyChecker::with_config(
+                CheckerConfig::new()
+                    .with_concurrency(2)
+                    .with_type_concurrency(ProxyType::Socks5, 3),
+            )
+            .with_check(check.clone());
+            let results = if streamed {
+                checker.check_with_progress(proxies.clone(), |_| {}).await.0
+            } else {
+                checker.check_proxies(proxies.clone()).await
+            };
+            assert_eq!(results.len(), proxies.len());
+
+            let state = check.0.lock().unwrap();
+            assert_eq!(state.peak[&ProxyType::Socks5], 3, "streamed: {}", streamed);
+            assert_eq!(state.peak[&ProxyType::Http], 2, "streamed: {}", streamed);
+            assert!(state.overlapped, "streamed: {}", streamed);
+        }
+    }
+
     #[test]
     fn test_proxy_checker_creation() {
         let checker = ProxyChecker::new();
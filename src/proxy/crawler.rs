@@ -0,0 +1,1005 @@
+//! Proxy crawler for collecting proxies from web sources
+
+use crate::proxy::models::{FullFormat, Proxy, ProxyType};
+use crate::proxy::parser::{is_yaml_file, ProxyParser};
+use crate::proxy::retry::RetryPolicy;
+use crate::proxy::seen::SeenSet;
+use crate::proxy::sink::{FileSink, OutputSink};
+use crate::Result;
+use anyhow::anyhow;
+use futures::stream::{self, StreamExt};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Default timeout for fetching a source in seconds
+const DEFAULT_TIMEOUT_SECS: u64 = 15;
+
+/// Default number of sources crawled concurrently
+const DEFAULT_CONCURRENCY: usize = 5;
+
+/// Default number of retries after a failed fetch
+const DEFAULT_MAX_RETRIES: usize = 2;
+
+/// Default delay between retries in milliseconds
+const DEFAULT_RETRY_DELAY_MS: u64 = 1000;
+
+/// Default body size (bytes) above which parsing moves to a blocking thread
+const DEFAULT_BLOCKING_PARSE_THRESHOLD: usize = 256 * 1024;
+
+/// User agent sent to sources, many of which reject unknown clients
+const DEFAULT_USER_AGENT: &str = "Mozilla/5.0 (compatible; open-proxy)";
+
+// Pre-compiled regex patterns for better performance
+static IP_PORT_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"\b((?:\d{1,3}\.){3}\d{1,3}):(\d{1,5})\b").expect("Invalid IP:PORT regex")
+});
+
+static TABLE_ROW_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r"(?is)<td[^>]*>\s*((?:\d{1,3}\.){3}\d{1,3})\s*</td>\s*<td[^>]*>\s*(\d{1,5})\s*</td>",
+    )
+    .expect("Invalid table row regex")
+});
+
+static ANCHOR_TAG_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?is)<(?:a|link)\b([^>]*)>").expect("Invalid anchor tag regex"));
+
+static REL_NEXT_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#"(?i)\brel\s*=\s*["']?[^"'>]*\bnext\b"#).expect("Invalid rel=next regex")
+});
+
+static HREF_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#"(?i)\bhref\s*=\s*(?:"([^"]*)"|'([^']*)'|([^\s>]+))"#).expect("Invalid href regex")
+});
+
+/// Placeholder replaced by the page number in [`Pagination::Template`] URLs
+pub const PAGE_PLACEHOLDER: &str = "{page}";
+
+/// How to reach the pages after the first of a paginated source
+///
+/// Pagination stops at `max_pages`, at the first page that adds no new
+/// endpoints, or at the first page that fails to load.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Pagination {
+    /// The source URL contains [`PAGE_PLACEHOLDER`], replaced by 1, 2, ...
+    Template { max_pages: usize },
+    /// Follow the page's `rel="next"` link
+    NextLink { max_pages: usize },
+}
+
+impl Pagination {
+    pub fn max_pages(&self) -> usize {
+        match self {
+            Pagination::Template { max_pages } | Pagination::NextLink { max_pages } => {
+                (*max_pages).max(1)
+            }
+        }
+    }
+}
+
+/// A web page or API endpoint listing proxies
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProxySource {
+    /// Human readable name used in reports
+    pub name: String,
+    /// URL to fetch
+    pub url: String,
+    /// Type assigned to proxies that don't specify a scheme
+    #[serde(default)]
+    pub proxy_type: ProxyType,
+    /// How to fetch further pages (`None` fetches only `url`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pagination: Option<Pagination>,
+}
+
+impl ProxySource {
+    pub fn new(name: String, url: String, proxy_type: ProxyType) -> Self {
+        Self {
+            name,
+            url,
+            proxy_type,
+            pagination: None,
+        }
+    }
+
+    pub fn with_pagination(mut self, pagination: Pagination) -> Self {
+        self.pagination = Some(pagination);
+        self
+    }
+
+    /// URL of page `page` (1-based) for template pagination, `url` otherwise
+    pub fn page_url(&self, page: usize) -> String {
+        match self.pagination {
+            Some(Pagination::Template { .. }) => {
+                self.url.replace(PAGE_PLACEHOLDER, &page.to_string())
+            }
+            _ => self.url.clone(),
+        }
+    }
+
+    /// Load a list of sources from a JSON file, or YAML for `.yaml`/`.yml` files
+    pub fn load_file<P: AsRef<Path>>(path: P) -> Result<Vec<ProxySource>> {
+        let path = path.as_ref();
+        let content = fs::read_to_string(path)?;
+        if is_yaml_file(path) {
+            return Ok(serde_yaml::from_str(&content)?);
+        }
+        Ok(serde_json::from_str(&content)?)
+    }
+
+    /// Save a list of sources as JSON, or YAML for `.yaml`/`.yml` files
+    pub fn save_file<P: AsRef<Path>>(sources: &[ProxySource], path: P) -> Result<()> {
+        let path = path.as_ref();
+        let content = if is_yaml_file(path) {
+            serde_yaml::to_string(sources)?
+        } else {
+            serde_json::to_string_pretty(sources)?
+        };
+        fs::write(path, content)?;
+        Ok(())
+    }
+}
+
+/// Configuration for the proxy crawler
+#[derive(Debug, Clone)]
+pub struct CrawlerConfig {
+    /// Timeout for each fetch
+    pub timeout: Duration,
+    /// Number of sources crawled concurrently
+    pub concurrency: usize,
+    /// When to retry a failed fetch
+    pub retry: RetryPolicy,
+    /// Consecutive failures after which a source is skipped for the rest of
+    /// the run (`None` disables the circuit breaker)
+    pub max_failures_per_source: Option<usize>,
+    /// User agent sent with every request
+    pub user_agent: String,
+    /// Bodies larger than this many bytes are parsed on a blocking thread so
+    /// they don't stall other crawls
+    pub blocking_parse_threshold: usize,
+    /// Sources that hit an error but still yield at least this many proxies
+    /// count as successful, keeping the error as a warning (`None`: any
+    /// error fails the source)
+    pub min_proxies: Option<usize>,
+}
+
+impl Default for CrawlerConfig {
+    fn default() -> Self {
+        Self {
+            timeout: Duration::from_secs(DEFAULT_TIMEOUT_SECS),
+            concurrency: DEFAULT_CONCURRENCY,
+            retry: RetryPolicy::fixed(
+                DEFAULT_MAX_RETRIES + 1,
+                Duration::from_millis(DEFAULT_RETRY_DELAY_MS),
+            ),
+            max_failures_per_source: None,
+            user_agent: DEFAULT_USER_AGENT.to_string(),
+            blocking_parse_threshold: DEFAULT_BLOCKING_PARSE_THRESHOLD,
+            min_proxies: None,
+        }
+    }
+}
+
+impl CrawlerConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    pub fn with_concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+
+    /// Retry a failed fetch up to `max_retries` times, waiting `retry_delay` in between
+    pub fn with_retries(self, max_retries: usize, retry_delay: Duration) -> Self {
+        self.with_retry_policy(RetryPolicy::fixed(max_retries + 1, retry_delay))
+    }
+
+    pub fn with_retry_policy(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    pub fn with_max_failures_per_source(mut self, max_failures: usize) -> Self {
+        self.max_failures_per_source = Some(max_failures.max(1));
+        self
+    }
+
+    pub fn with_blocking_parse_threshold(mut self, bytes: usize) -> Self {
+        self.blocking_parse_threshold = bytes;
+        self
+    }
+
+    pub fn with_min_proxies(mut self, min_proxies: usize) -> Self {
+        self.min_proxies = Some(min_proxies.max(1));
+        self
+    }
+}
+
+/// Outcome of crawling a single source
+#[derive(Debug, Clone)]
+pub struct CrawlResult {
+    pub source: ProxySource,
+    pub proxies: Vec<Proxy>,
+    pub error: Option<String>,
+    /// Error hit by a source that still yielded enough proxies to count as a
+    /// success (see [`CrawlerConfig::min_proxies`])
+    pub warning: Option<String>,
+    /// Whether the source was abandoned after too many consecutive failures
+    pub tripped: bool,
+}
+
+impl CrawlResult {
+    pub fn is_success(&self) -> bool {
+        self.error.is_none()
+    }
+}
+
+/// Crawler fetching proxy lists from web sources
+pub struct ProxyCrawler {
+    config: CrawlerConfig,
+    client: Client,
+    /// Consecutive failures per source URL during this run
+    failures: Mutex<HashMap<String, usize>>,
+    /// Sources abandoned by the circuit breaker during this run
+    tripped: Mutex<HashSet<String>>,
+}
+
+impl ProxyCrawler {
+    /// Create a new crawler with default configuration
+    pub fn new() -> Self {
+        Self::with_config(CrawlerConfig::default())
+    }
+
+    /// Create a new crawler with custom configuration
+    pub fn with_config(config: CrawlerConfig) -> Self {
+        let client = Client::builder()
+            .timeout(config.timeout)
+            .user_agent(config.user_agent.clone())
+            .build()
+            .unwrap_or_default();
+
+        Self {
+            config,
+            client,
+            failures: Mutex::new(HashMap::new()),
+            tripped: Mutex::new(HashSet::new()),
+        }
+    }
+
+    /// Fetch the body of a URL, failing on non-success HTTP statuses
+    pub async fn crawl_url(&self, url: &str) -> Result<String> {
+        match self.fetch_page(url).await? {
+            (body, None) => Ok(body),
+            (_, Some(error)) => Err(anyhow!(error)),
+        }
+    }
+
+    /// Fetch the body of a URL, keeping what arrived if the transfer is cut short
+    ///
+    /// Returns the body and, when it is partial, the error that interrupted it.
+    async fn fetch_page(&self, url: &str) -> Result<(String, Option<String>)> {
+        let mut response = self.client.get(url).send().await?;
+        if !response.status().is_success() {
+            return Err(anyhow!("HTTP status: {}", response.status()));
+        }
+
+        let mut body = Vec::new();
+        let error = loop {
+            match response.chunk().await {
+                Ok(Some(chunk)) => body.extend_from_slice(&chunk),
+                Ok(None) => break None,
+                Err(e) if body.is_empty() => return Err(e.into()),
+                Err(e) => break Some(e.to_string()),
+            }
+        };
+        Ok((String::from_utf8_lossy(&body).into_owned(), error))
+    }
+
+    /// Crawl a single source, retrying failed fetches
+    ///
+    /// Paginated sources are followed page by page (see [`Pagination`]). If a
+    /// later page fails or a page is cut short, the proxies found so far are
+    /// kept, and the source still counts as a success when they reach
+    /// [`CrawlerConfig::min_proxies`]. When the circuit breaker
+    /// is enabled, a source that fails `max_failures_per_source` times in a
+    /// row is marked as tripped and skipped by every later call during this
+    /// run.
+    pub async fn crawl_source(&self, source: &ProxySource) -> CrawlResult {
+        if self.is_tripped(source) {
+            return CrawlResult {
+                source: source.clone(),
+                proxies: Vec::new(),
+                error: Some("skipped: too many consecutive failures".to_string()),
+                warning: None,
+                tripped: true,
+            };
+        }
+
+        let max_pages = source.pagination.map_or(1, |p| p.max_pages());
+        let mut url = source.page_url(1);
+        let mut seen = HashSet::new();
+        let mut proxies = Vec::new();
+        let mut page_error = None;
+
+        for page in 1..=max_pages {
+            let (body, partial_error) = match self.fetch_with_retries(source, &url).await {
+                Ok(page) => page,
+                Err((error, tripped)) if page == 1 => {
+                    return CrawlResult {
+                        source: source.clone(),
+                        proxies: Vec::new(),
+                        error: Some(error),
+                        warning: None,
+                        tripped,
+                    };
+                }
+                Err((error, _)) => {
+                    page_error = Some(error);
+                    break;
+                }
+            };
+
+            let next = match source.pagination {
+                Some(Pagination::Template { .. }) => Some(source.page_url(page + 1)),
+                Some(Pagination::NextLink { .. }) => find_next_link(&body, &url),
+                None => None,
+            };
+
+            let before = proxies.len();
+            for proxy in self.parse_body(body, source.proxy_type.clone()).await {
+                if seen.insert((proxy.host.clone(), proxy.port)) {
+                    proxies.push(proxy);
+                }
+            }
+            if partial_error.is_some() {
+                page_error = partial_error;
+                break;
+            }
+
+            match next {
+                Some(next) if proxies.len() > before => url = next,
+                _ => break,
+            }
+        }
+
+        self.apply_min_proxies(CrawlResult {
+            source: source.clone(),
+            proxies,
+            error: page_error,
+            warning: None,
+            tripped: false,
+        })
+    }
+
+    /// Turn the error of a result that still reached
+    /// [`CrawlerConfig::min_proxies`] into a warning
+    ///
+    /// Every result handed out by the crawler goes through this, whether the
+    /// error hit a later page or cut a single page short.
+    fn apply_min_proxies(&self, mut result: CrawlResult) -> CrawlResult {
+        let enough = self
+            .config
+            .min_proxies
+            .is_some_and(|min| result.proxies.len() >= min);
+        if enough && !result.tripped && result.error.is_some() {
+            result.warning = result.error.take();
+        }
+        result
+    }
+
+    /// Fetch one page of `source`, returning the last error and whether the
+    /// source tripped if every attempt failed
+    ///
+    /// A body cut short counts as a failed attempt; if no attempt does
+    /// better, the longest partial body is returned with its error.
+    async fn fetch_with_retries(
+        &self,
+        source: &ProxySource,
+        url: &str,
+    ) -> std::result::Result<(String, Option<String>), (String, bool)> {
+        let mut last_error = String::new();
+        let mut partial: Option<(String, Option<String>)> = None;
+        for attempt in 0..self.config.retry.max_attempts {
+            if attempt > 0 {
+                tokio::time::sleep(self.config.retry.next_delay(attempt)).await;
+            }
+
+            let tripped = match self.fetch_page(url).await {
+                Ok((body, None)) => {
+                    self.record_success(source);
+                    return Ok((body, None));
+                }
+                Ok((body, Some(error))) => {
+                    if partial
+                        .as_ref()
+                        .is_none_or(|(kept, _)| body.len() > kept.len())
+                    {
+                        partial = Some((body, Some(error.clone())));
+                    }
+                    last_error = error;
+                    self.record_failure(source)
+                }
+                Err(e) => {
+                    last_error = e.to_string();
+                    self.record_failure(source)
+                }
+            };
+            if tripped {
+                return partial.ok_or((last_error, true));
+            }
+        }
+        partial.ok_or((last_error, false))
+    }
+
+    /// Extract proxies from a fetched body, off the async runtime when it's large
+    pub async fn parse_body(&self, body: String, default_type: ProxyType) -> Vec<Proxy> {
+        if body.len() <= self.config.blocking_parse_threshold {
+            return parse_proxies_from_text(&body, default_type);
+        }
+
+        // The blocking task only fails if parsing panicked; treat that as an empty page
+        tokio::task::spawn_blocking(move || parse_proxies_from_text(&body, default_type))
+            .await
+            .unwrap_or_default()
+    }
+
+    /// Crawl several sources concurrently, returning one result per source in order
+    pub async fn crawl_sources_with_results(&self, sources: &[ProxySource]) -> Vec<CrawlResult> {
+        stream::iter(sources)
+            .map(|source| self.crawl_source(source))
+            .buffered(self.config.concurrency)
+            .collect()
+            .await
+    }
+
+    /// Crawl several sources and merge their proxies, dropping duplicate endpoints
+    pub async fn crawl_sources(&self, sources: &[ProxySource]) -> Vec<Proxy> {
+        let mut seen = HashSet::new();
+        self.crawl_sources_with_results(sources)
+            .await
+            .into_iter()
+            .flat_map(|result| result.proxies)
+            .filter(|proxy| seen.insert((proxy.host.clone(), proxy.port)))
+            .collect()
+    }
+
+    /// Crawl several sources, appending each new endpoint to `path` as soon as
+    /// its source finishes
+    ///
+    /// Only the set of endpoints already written is kept in memory, so huge
+    /// crawls don't need to hold every proxy at once. Proxies are written in
+    /// `IP:PORT[:USER:PASS]` format, and `on_result` is called once per source
+    /// with its result and the number of new endpoints it contributed. Returns
+    /// the number of unique proxies written.
+    pub async fn crawl_sources_to_file<P, F>(
+        &self,
+        sources: &[ProxySource],
+        path: P,
+        on_result: F,
+    ) -> Result<usize>
+    where
+        P: AsRef<Path>,
+        F: FnMut(&CrawlResult, usize),
+    {
+        let mut sink = FileSink::create(path, FullFormat::ColonAll)?;
+        let mut seen = HashSet::new();
+        self.stream_sources_to_sink(
+            sources,
+            &mut sink,
+            |proxy| Ok(seen.insert((proxy.host.clone(), proxy.port))),
+            on_result,
+        )
+        .await
+    }
+
+    /// Crawl several sources, writing each endpoint not in `seen` to `sink`
+    /// as soon as its source finishes
+    ///
+    /// New endpoints are recorded in `seen`, so with a persistent
+    /// [`SeenSet`] proxies emitted by earlier runs aren't written again.
+    /// `on_result` is called as for [`ProxyCrawler::crawl_sources_to_file`].
+    /// Returns the number of proxies written.
+    pub async fn crawl_new_sources_to_sink<F>(
+        &self,
+        sources: &[ProxySource],
+        sink: &mut dyn OutputSink,
+        seen: &mut SeenSet,
+        on_result: F,
+    ) -> Result<usize>
+    where
+        F: FnMut(&CrawlResult, usize),
+    {
+        let written = self
+            .stream_sources_to_sink(sources, sink, |proxy| seen.insert(proxy), on_result)
+            .await?;
+        seen.flush()?;
+        Ok(written)
+    }
+
+    async fn stream_sources_to_sink<N, F>(
+        &self,
+        sources: &[ProxySource],
+        sink: &mut dyn OutputSink,
+        mut is_new: N,
+        mut on_result: F,
+    ) -> Result<usize>
+    where
+        N: FnMut(&Proxy) -> Result<bool>,
+        F: FnMut(&CrawlResult, usize),
+    {
+        let mut total = 0;
+
+        let mut results = stream::iter(sources)
+            .map(|source| self.crawl_source(source))
+            .buffer_unordered(self.config.concurrency);
+
+        while let Some(result) = results.next().await {
+            let mut written = 0;
+            for proxy in &result.proxies {
+                if is_new(proxy)? {
+                    sink.write_proxy(proxy)?;
+                    written += 1;
+                }
+            }
+            sink.flush()?;
+            total += written;
+            on_result(&result, written);
+        }
+
+        Ok(total)
+    }
+
+    fn is_tripped(&self, source: &ProxySource) -> bool {
+        self.tripped
+            .lock()
+            .expect("Crawler state poisoned")
+            .contains(&source.url)
+    }
+
+    fn record_success(&self, source: &ProxySource) {
+        self.failures
+            .lock()
+            .expect("Crawler state poisoned")
+            .remove(&source.url);
+    }
+
+    /// Count a failure, returning whether the source has now tripped
+    fn record_failure(&self, source: &ProxySource) -> bool {
+        let Some(max_failures) = self.config.max_failures_per_source else {
+            return false;
+        };
+
+        let mut failures = self.failures.lock().expect("Crawler state poisoned");
+        let count = failures.entry(source.url.clone()).or_default();
+        *count += 1;
+        if *count < max_failures {
+            return false;
+        }
+
+        self.tripped
+            .lock()
+            .expect("Crawler state poisoned")
+            .insert(source.url.clone());
+        true
+    }
+}
+
+impl Default for ProxyCrawler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Extract proxies from a fetched page
+///
+/// Tries, in order: one proxy per line (any format [`ProxyParser`] accepts),
+/// `IP:PORT` pairs anywhere in the text, and HTML tables with the IP and port
+/// in adjacent cells. Duplicate endpoints are dropped, keeping the first.
+pub fn parse_proxies_from_text(text: &str, default_type: ProxyType) -> Vec<Proxy> {
+    let mut seen = HashSet::new();
+    let mut proxies = Vec::new();
+    let mut push = |proxy: Proxy| {
+        if seen.insert((proxy.host.clone(), proxy.port)) {
+            proxies.push(proxy);
+        }
+    };
+
+    for proxy in ProxyParser::parse_string(text, default_type.clone()) {
+        push(proxy);
+    }
+
+    for caps in IP_PORT_REGEX.captures_iter(text) {
+        if let Ok(port) = caps[2].parse::<u16>() {
+            push(Proxy::new(caps[1].to_string(), port, default_type.clone()));
+        }
+    }
+
+    for caps in TABLE_ROW_REGEX.captures_iter(text) {
+        if let Ok(port) = caps[2].parse::<u16>() {
+            push(Proxy::new(caps[1].to_string(), port, default_type.clone()));
+        }
+    }
+
+    proxies
+}
+
+/// Find the `rel="next"` link in a page, resolved against the page's URL
+fn find_next_link(body: &str, page_url: &str) -> Option<String> {
+    let base = reqwest::Url::parse(page_url).ok()?;
+    ANCHOR_TAG_REGEX
+        .captures_iter(body)
+        .map(|tag| tag[1].to_string())
+        .filter(|attrs| REL_NEXT_REGEX.is_match(attrs))
+        .find_map(|attrs| {
+            let caps = HREF_REGEX.captures(&attrs)?;
+            let href = caps.get(1).or(caps.get(2)).or(caps.get(3))?.as_str();
+            let next = base.join(&href.replace("&amp;", "&")).ok()?;
+            Some(next.to_string())
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proxy::test_support::{spawn_http_server, MockReply};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    #[test]
+    fn test_sources_yaml_round_trip() {
+        let path =
+            std::env::temp_dir().join(format!("open-proxy-sources-{}.yml", uuid::Uuid::new_v4()));
+        let sources = vec![
+            ProxySource::new(
+                "list".to_string(),
+                "https://example.com/proxies.txt".to_string(),
+                ProxyType::Http,
+            ),
+            ProxySource::new(
+                "socks".to_string(),
+                "https://example.com/socks.txt".to_string(),
+                ProxyType::Socks5,
+            ),
+        ];
+
+        ProxySource::save_file(&sources, &path).unwrap();
+        let content = fs::read_to_string(&path).unwrap();
+        assert!(content.contains("name: socks"), "{}", content);
+        assert_eq!(ProxySource::load_file(&path).unwrap(), sources);
+
+        fs::remove_file(path).unwrap();
+    }
+
+    fn source_for(addr: std::net::SocketAddr) -> ProxySource {
+        ProxySource::new(
+            "mock".to_string(),
+            format!("http://{}/proxies", addr),
+            ProxyType::Http,
+        )
+    }
+
+    #[test]
+    fn test_parse_proxies_from_plain_list() {
+        let text = "1.2.3.4:8080\nsocks5://5.6.7.8:1080\n# comment\n1.2.3.4:8080\n";
+        let proxies = parse_proxies_from_text(text, ProxyType::Http);
+        assert_eq!(proxies.len(), 2);
+        assert_eq!(proxies[1].proxy_type, ProxyType::Socks5);
+    }
+
+    #[test]
+    fn test_parse_proxies_from_html() {
+        let text = r#"
+            <p>Fresh proxies: 9.9.9.9:3128, 8.8.8.8:80</p>
+            <table>
+              <tr><td>10.0.0.1</td><td>8080</td><td>US</td></tr>
+              <tr><td class="ip"> 10.0.0.2 </td>
+                  <td>3128</td></tr>
+            </table>
+        "#;
+        let proxies = parse_proxies_from_text(text, ProxyType::Http);
+        let endpoints: Vec<_> = proxies.iter().map(|p| p.to_simple_string()).collect();
+        assert_eq!(
+            endpoints,
+            vec![
+                "9.9.9.9:3128",
+                "8.8.8.8:80",
+                "10.0.0.1:8080",
+                "10.0.0.2:3128"
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_crawl_source() {
+        let addr = spawn_http_server(|_| MockReply::new(200, "1.1.1.1:80\n2.2.2.2:8080\n")).await;
+        let crawler = ProxyCrawler::new();
+
+        let result = crawler.crawl_source(&source_for(addr)).await;
+        assert!(result.is_success());
+        assert!(!result.tripped);
+        assert_eq!(result.proxies.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_crawl_source_follows_page_template() {
+        let addr = spawn_http_server(|req| match req.target.as_str() {
+            "/list?page=1" => MockReply::new(200, "1.1.1.1:80\n2.2.2.2:80\n"),
+            "/list?page=2" => MockReply::new(200, "3.3.3.3:80\n"),
+            // Out-of-range pages repeat the last page
+            _ => MockReply::new(200, "3.3.3.3:80\n"),
+        })
+        .await;
+        let source = ProxySource::new(
+            "paged".to_string(),
+            format!("http://{}/list?page={{page}}", addr),
+            ProxyType::Http,
+        )
+        .with_pagination(Pagination::Template { max_pages: 10 });
+
+        let result = ProxyCrawler::new().crawl_source(&source).await;
+        assert!(result.is_success());
+        let endpoints: Vec<_> = result
+            .proxies
+            .iter()
+            .map(|p| p.to_simple_string())
+            .collect();
+        assert_eq!(endpoints, vec!["1.1.1.1:80", "2.2.2.2:80", "3.3.3.3:80"]);
+    }
+
+    #[tokio::test]
+    async fn test_partial_source_succeeds_with_enough_proxies() {
+        let addr = spawn_http_server(|req| match req.target.as_str() {
+            "/list?page=1" => MockReply::new(200, "1.1.1.1:80\n2.2.2.2:80\n"),
+            _ => MockReply::new(503, "try again later"),
+        })
+        .await;
+        let source = ProxySource::new(
+            "flaky".to_string(),
+            format!("http://{}/list?page={{page}}", addr),
+            ProxyType::Http,
+        )
+        .with_pagination(Pagination::Template { max_pages: 5 });
+        let config = CrawlerConfig::new().with_retries(0, Duration::from_millis(1));
+
+        // By default the failed page fails the source, keeping what it found
+        let result = ProxyCrawler::with_config(config.clone())
+            .crawl_source(&source)
+            .await;
+        assert!(!result.is_success());
+        assert_eq!(result.proxies.len(), 2);
+
+        let result = ProxyCrawler::with_config(config.clone().with_min_proxies(2))
+            .crawl_source(&source)
+            .await;
+        assert!(result.is_success());
+        assert_eq!(result.proxies.len(), 2);
+        assert_eq!(
+            result.warning.as_deref(),
+            Some("HTTP status: 503 Service Unavailable")
+        );
+
+        let result = ProxyCrawler::with_config(config.with_min_proxies(3))
+            .crawl_source(&source)
+            .await;
+        assert!(!result.is_success());
+        assert!(result.warning.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_single_page_cut_short_succeeds_with_enough_proxies() {
+        let addr = spawn_http_server(|_| {
+            MockReply::new(200, "1.1.1.1:80\n2.2.2.2:80\n3.3.3.3:80\n").truncated(4096)
+        })
+        .await;
+        let source = source_for(addr);
+        let config = CrawlerConfig::new().with_retries(1, Duration::from_millis(1));
+
+        let result = ProxyCrawler::with_config(config.clone())
+            .crawl_source(&source)
+            .await;
+        assert!(!result.is_success());
+        assert_eq!(result.proxies.len(), 3);
+
+        let crawler = ProxyCrawler::with_config(config.with_min_proxies(3));
+        let results = crawler
+            .crawl_sources_with_results(std::slice::from_ref(&source))
+            .await;
+        assert!(results[0].is_success(), "{:?}", results[0].error);
+        assert_eq!(results[0].proxies.len(), 3);
+        assert!(results[0].warning.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_crawl_source_follows_next_links_up_to_cap() {
+        let requests = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&requests);
+        let addr = spawn_http_server(move |req| {
+            counter.fetch_add(1, Ordering::SeqCst);
+            let page: usize = req.target.trim_start_matches("/p/").parse().unwrap_or(1);
+            MockReply::new(
+                200,
+                format!(
+                    "<p>10.0.0.{}:8080</p><a class=\"btn\" rel=\"next\" href=\"/p/{}\">Next</a>",
+                    page,
+                    page + 1
+                ),
+            )
+        })
+        .await;
+        let source = ProxySource::new(
+            "linked".to_string(),
+            format!("http://{}/p/1", addr),
+            ProxyType::Http,
+        )
+        .with_pagination(Pagination::NextLink { max_pages: 3 });
+
+        let result = ProxyCrawler::new().crawl_source(&source).await;
+        assert_eq!(result.proxies.len(), 3);
+        assert_eq!(result.proxies[2].host, "10.0.0.3");
+        assert_eq!(requests.load(Ordering::SeqCst), 3);
+    }
+
+    #[test]
+    fn test_find_next_link() {
+        let body =
+            r#"<link rel="prev" href="?page=1"><a href='?page=3&amp;sort=new' rel='next'>»</a>"#;
+        assert_eq!(
+            find_next_link(body, "https://example.com/list?page=2").as_deref(),
+            Some("https://example.com/list?page=3&sort=new")
+        );
+        assert_eq!(
+            find_next_link("<a href=\"/x\">x</a>", "https://example.com/"),
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn test_circuit_breaker_abandons_failing_source() {
+        let requests = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&requests);
+        let addr = spawn_http_server(move |_| {
+            counter.fetch_add(1, Ordering::SeqCst);
+            MockReply::new(429, "slow down")
+        })
+        .await;
+
+        let crawler = ProxyCrawler::with_config(
+            CrawlerConfig::new()
+                .with_retries(10, Duration::from_millis(1))
+                .with_max_failures_per_source(3),
+        );
+        let source = source_for(addr);
+
+        let result = crawler.crawl_source(&source).await;
+        assert!(result.tripped);
+        assert!(!result.is_success());
+        assert_eq!(requests.load(Ordering::SeqCst), 3);
+
+        // The source stays tripped for the rest of the run
+        let result = crawler.crawl_source(&source).await;
+        assert!(result.tripped);
+        assert_eq!(requests.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_failing_source_without_circuit_breaker_uses_all_retries() {
+        let requests = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&requests);
+        let addr = spawn_http_server(move |_| {
+            counter.fetch_add(1, Ordering::SeqCst);
+            MockReply::new(500, "error")
+        })
+        .await;
+
+        let crawler = ProxyCrawler::with_config(
+            CrawlerConfig::new().with_retries(4, Duration::from_millis(1)),
+        );
+
+        let result = crawler.crawl_source(&source_for(addr)).await;
+        assert!(!result.tripped);
+        assert_eq!(
+            result.error.as_deref(),
+            Some("HTTP status: 500 Internal Server Error")
+        );
+        assert_eq!(requests.load(Ordering::SeqCst), 5);
+    }
+
+    #[tokio::test]
+    async fn test_crawl_sources_to_file_writes_unique_endpoints() {
+        let addr = spawn_http_server(|req| match req.target.as_str() {
+            "/a" => MockReply::new(200, "1.1.1.1:80\n2.2.2.2:8080\n"),
+            "/b" => MockReply::new(200, "2.2.2.2:8080\n3.3.3.3:3128\n1.1.1.1:80\n"),
+            _ => MockReply::new(404, "not found"),
+        })
+        .await;
+        let sources: Vec<_> = ["a", "b", "missing"]
+            .iter()
+            .map(|name| {
+                ProxySource::new(
+                    name.to_string(),
+                    format!("http://{}/{}", addr, name),
+                    ProxyType::Http,
+                )
+            })
+            .collect();
+
+        let path =
+            std::env::temp_dir().join(format!("open-proxy-crawl-{}.txt", uuid::Uuid::new_v4()));
+        let crawler = ProxyCrawler::with_config(
+            CrawlerConfig::new().with_retries(0, Duration::from_millis(1)),
+        );
+        let mut reported = Vec::new();
+        let written = crawler
+            .crawl_sources_to_file(&sources, &path, |result, new| {
+                reported.push((result.source.name.clone(), result.is_success(), new))
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(written, 3);
+        reported.sort();
+        assert_eq!(reported.len(), 3);
+        assert_eq!(reported[2], ("missing".to_string(), false, 0));
+        assert_eq!(reported[0].2 + reported[1].2, 3);
+
+        let mut lines: Vec<_> = fs::read_to_string(&path)
+            .unwrap()
+            .lines()
+            .map(str::to_string)
+            .collect();
+        lines.sort();
+        assert_eq!(lines, vec!["1.1.1.1:80", "2.2.2.2:8080", "3.3.3.3:3128"]);
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_large_body_parsing_does_not_block_runtime() {
+        let body: String = (0..60_000)
+            .map(|i| {
+                format!(
+                    "<tr><td>10.{}.{}.{}</td><td>8080</td></tr>\n",
+                    i / 65536,
+                    (i / 256) % 256,
+                    i % 256
+                )
+            })
+            .collect();
+        let crawler =
+            ProxyCrawler::with_config(CrawlerConfig::new().with_blocking_parse_threshold(1024));
+
+        // On the single-threaded test runtime, the ticker can only finish
+        // before parsing does if parsing runs on another thread
+        let ticker = tokio::spawn(async {
+            let start = std::time::Instant::now();
+            tokio::time::sleep(Duration::from_millis(1)).await;
+            start.elapsed()
+        });
+        let start = std::time::Instant::now();
+        let proxies = crawler.parse_body(body, ProxyType::Http).await;
+        let parse_elapsed = start.elapsed();
+        let ticker_elapsed = ticker.await.unwrap();
+
+        assert_eq!(proxies.len(), 60_000);
+        assert!(
+            ticker_elapsed < parse_elapsed,
+            "ticker {:?} vs parse {:?}",
+            ticker_elapsed,
+            parse_elapsed
+        );
+    }
+}
@@ -0,0 +1,723 @@
+//! Geolocation of proxy hosts
+//!
+//! Lookups go through the [`GeoLocator`] trait so callers can plug in a
+//! MaxMind database ([`MmdbGeoLocator`]) or any other source.
+
+use crate::proxy::models::{Proxy, ProxyCheckResult};
+use crate::Result;
+use anyhow::anyhow;
+use maxminddb::{geoip2, Reader};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::net::IpAddr;
+use std::path::Path;
+use std::sync::Mutex;
+
+/// Label used for proxies whose country can't be determined
+pub const UNKNOWN_COUNTRY: &str = "Unknown";
+
+/// Default maximum number of entries written to a cache file
+const DEFAULT_MAX_CACHE_ENTRIES: usize = 100_000;
+
+/// Version of the on-disk cache format (2 added ASN fields)
+const CACHE_FILE_VERSION: u32 = 2;
+
+/// Geolocation details for an IP address
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GeoInfo {
+    /// ISO 3166-1 alpha-2 country code (e.g. `US`)
+    pub country_code: Option<String>,
+    /// English country name
+    pub country_name: Option<String>,
+    /// English city name
+    pub city: Option<String>,
+    /// Autonomous system number of the network
+    pub asn: Option<u32>,
+    /// Organization operating the autonomous system
+    pub as_org: Option<String>,
+}
+
+/// Source of geolocation data
+pub trait GeoLocator: Send + Sync {
+    /// Look up an IP address, returning `None` when it isn't known
+    fn lookup(&self, ip: IpAddr) -> Option<GeoInfo>;
+
+    /// Look up a proxy host; hostnames that aren't IP addresses aren't resolved
+    fn lookup_host(&self, host: &str) -> Option<GeoInfo> {
+        host.parse::<IpAddr>().ok().and_then(|ip| self.lookup(ip))
+    }
+}
+
+/// Kind of MaxMind database, detected from its `database_type` metadata
+///
+/// | Edition | Examples | [`GeoInfo`] fields filled |
+/// |---------|----------|---------------------------|
+/// | `City` | GeoLite2-City, GeoIP2-City, GeoIP2-Enterprise, DBIP-City-Lite | country code and name, city |
+/// | `Country` | GeoLite2-Country, GeoIP2-Country, DBIP-Country-Lite | country code and name |
+/// | `Asn` | GeoLite2-ASN, GeoIP2-ISP, DBIP-ASN-Lite | ASN and its organization |
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MmdbEdition {
+    City,
+    Country,
+    Asn,
+}
+
+impl MmdbEdition {
+    /// Detect the edition from a database type such as `GeoLite2-City`
+    pub fn from_database_type(database_type: &str) -> Option<Self> {
+        let database_type = database_type.to_ascii_lowercase();
+        if database_type.contains("city") || database_type.contains("enterprise") {
+            Some(MmdbEdition::City)
+        } else if database_type.contains("country") {
+            Some(MmdbEdition::Country)
+        } else if database_type.contains("asn") || database_type.contains("isp") {
+            Some(MmdbEdition::Asn)
+        } else {
+            None
+        }
+    }
+}
+
+/// Geolocator backed by a MaxMind (GeoLite2/GeoIP2) database file
+///
+/// City, Country and ASN editions are all accepted (see [`MmdbEdition`]);
+/// an ASN database can be added next to a City or Country one with
+/// [`MmdbGeoLocator::with_asn`].
+pub struct MmdbGeoLocator {
+    reader: Reader<Vec<u8>>,
+    edition: MmdbEdition,
+    asn: Option<Reader<Vec<u8>>>,
+}
+
+impl MmdbGeoLocator {
+    /// Open a `.mmdb` database file
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::from_reader(Reader::open_readfile(path)?)
+    }
+
+    /// Read a database already loaded into memory
+    pub fn from_bytes(bytes: Vec<u8>) -> Result<Self> {
+        Self::from_reader(Reader::from_source(bytes)?)
+    }
+
+    fn from_reader(reader: Reader<Vec<u8>>) -> Result<Self> {
+        let database_type = &reader.metadata.database_type;
+        let edition = MmdbEdition::from_database_type(database_type)
+            .ok_or_else(|| anyhow!("Unsupported MaxMind database type: {}", database_type))?;
+        Ok(Self {
+            reader,
+            edition,
+            asn: None,
+        })
+    }
+
+    pub fn edition(&self) -> MmdbEdition {
+        self.edition
+    }
+
+    /// Fill in [`GeoInfo::asn`] and [`GeoInfo::as_org`] from an ASN database
+    pub fn with_asn(mut self, asn: MmdbGeoLocator) -> Result<Self> {
+        if asn.edition != MmdbEdition::Asn {
+            return Err(anyhow!(
+                "{} is not an ASN database",
+                asn.reader.metadata.database_type
+            ));
+        }
+        self.asn = Some(asn.reader);
+        Ok(self)
+    }
+}
+
+impl GeoLocator for MmdbGeoLocator {
+    fn lookup(&self, ip: IpAddr) -> Option<GeoInfo> {
+        let geo = decode_record(&self.reader, self.edition, ip);
+        let Some(asn) = self
+            .asn
+            .as_ref()
+            .and_then(|reader| decode_record(reader, MmdbEdition::Asn, ip))
+        else {
+            return geo;
+        };
+
+        let mut geo = geo.unwrap_or_default();
+        geo.asn = asn.asn;
+        geo.as_org = asn.as_org;
+        Some(geo)
+    }
+}
+
+/// Look up `ip`, decoding the record the way `edition` lays it out
+fn decode_record(reader: &Reader<Vec<u8>>, edition: MmdbEdition, ip: IpAddr) -> Option<GeoInfo> {
+    match edition {
+        MmdbEdition::City => {
+            let record: geoip2::City = reader.lookup(ip).ok()?;
+            let country = record.country.as_ref();
+            Some(GeoInfo {
+                country_code: country.and_then(|c| c.iso_code).map(str::to_string),
+                country_name: english_name(country.and_then(|c| c.names.as_ref())),
+                city: english_name(record.city.as_ref().and_then(|c| c.names.as_ref())),
+                ..GeoInfo::default()
+            })
+        }
+        MmdbEdition::Country => {
+            let record: geoip2::Country = reader.lookup(ip).ok()?;
+            let country = record.country.as_ref();
+            Some(GeoInfo {
+                country_code: country.and_then(|c| c.iso_code).map(str::to_string),
+                country_name: english_name(country.and_then(|c| c.names.as_ref())),
+                ..GeoInfo::default()
+            })
+        }
+        MmdbEdition::Asn => {
+            let record: geoip2::Asn = reader.lookup(ip).ok()?;
+            Some(GeoInfo {
+                asn: record.autonomous_system_number,
+                as_org: record.autonomous_system_organization.map(str::to_string),
+                ..GeoInfo::default()
+            })
+        }
+    }
+}
+
+fn english_name(names: Option<&BTreeMap<&str, &str>>) -> Option<String> {
+    names
+        .and_then(|names| names.get("en"))
+        .map(|name| name.to_string())
+}
+
+/// On-disk representation of a geo cache
+#[derive(Serialize, Deserialize)]
+struct CacheFile {
+    version: u32,
+    entries: Vec<CacheEntry>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct CacheEntry {
+    ip: IpAddr,
+    geo: Option<GeoInfo>,
+}
+
+/// In-memory cache entry with a recency stamp used when trimming
+struct CachedLookup {
+    geo: Option<GeoInfo>,
+    last_used: u64,
+}
+
+#[derive(Default)]
+struct CacheState {
+    entries: HashMap<IpAddr, CachedLookup>,
+    clock: u64,
+}
+
+/// Geolocator wrapper caching lookups, optionally persisted between runs
+///
+/// Misses are cached too, so unknown addresses aren't looked up again. The
+/// cache file is a small JSON sidecar; at most `max_entries` of the most
+/// recently used entries are written.
+pub struct CachedGeoLocator<L> {
+    inner: L,
+    state: Mutex<CacheState>,
+    max_entries: usize,
+}
+
+impl<L: GeoLocator> CachedGeoLocator<L> {
+    pub fn new(inner: L) -> Self {
+        Self {
+            inner,
+            state: Mutex::new(CacheState::default()),
+            max_entries: DEFAULT_MAX_CACHE_ENTRIES,
+        }
+    }
+
+    pub fn with_max_entries(mut self, max_entries: usize) -> Self {
+        self.max_entries = max_entries;
+        self
+    }
+
+    /// Number of cached lookups
+    pub fn len(&self) -> usize {
+        self.state.lock().expect("Geo cache poisoned").entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Load entries from a cache file, returning how many were loaded
+    ///
+    /// A missing, unreadable or corrupt file is not an error: the cache just
+    /// starts empty and the file is overwritten on the next save.
+    pub fn load_cache<P: AsRef<Path>>(&self, path: P) -> usize {
+        let Ok(content) = fs::read_to_string(path) else {
+            return 0;
+        };
+        let Ok(file) = serde_json::from_str::<CacheFile>(&content) else {
+            return 0;
+        };
+        if file.version != CACHE_FILE_VERSION {
+            return 0;
+        }
+
+        let mut state = self.state.lock().expect("Geo cache poisoned");
+        let loaded = file.entries.len();
+        for entry in file.entries {
+            state.entries.insert(
+                entry.ip,
+                CachedLookup {
+                    geo: entry.geo,
+                    last_used: 0,
+                },
+            );
+        }
+        loaded
+    }
+
+    /// Write the most recently used entries to a cache file
+    pub fn save_cache<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let path = path.as_ref();
+        let mut entries: Vec<_> = {
+            let state = self.state.lock().expect("Geo cache poisoned");
+            state
+                .entries
+                .iter()
+                .map(|(ip, cached)| (cached.last_used, *ip, cached.geo.clone()))
+                .collect()
+        };
+        entries.sort_by_key(|entry| std::cmp::Reverse(entry.0));
+        entries.truncate(self.max_entries);
+
+        let file = CacheFile {
+            version: CACHE_FILE_VERSION,
+            entries: entries
+                .into_iter()
+                .map(|(_, ip, geo)| CacheEntry { ip, geo })
+                .collect(),
+        };
+
+        // Write to a temporary file first so a crash can't leave a truncated cache
+        let tmp_path = path.with_extension("tmp");
+        fs::write(&tmp_path, serde_json::to_vec(&file)?)?;
+        fs::rename(&tmp_path, path)?;
+        Ok(())
+    }
+}
+
+impl<L: GeoLocator> GeoLocator for CachedGeoLocator<L> {
+    fn lookup(&self, ip: IpAddr) -> Option<GeoInfo> {
+        {
+            let mut state = self.state.lock().expect("Geo cache poisoned");
+            state.clock += 1;
+            let clock = state.clock;
+            if let Some(cached) = state.entries.get_mut(&ip) {
+                cached.last_used = clock;
+                return cached.geo.clone();
+            }
+        }
+
+        let geo = self.inner.lookup(ip);
+        let mut state = self.state.lock().expect("Geo cache poisoned");
+        let last_used = state.clock;
+        state.entries.insert(
+            ip,
+            CachedLookup {
+                geo: geo.clone(),
+                last_used,
+            },
+        );
+        geo
+    }
+}
+
+/// Count proxies per country code, sorted by count descending
+///
+/// Proxies that can't be placed are counted under [`UNKNOWN_COUNTRY`].
+pub fn count_by_country(proxies: &[Proxy], locator: &dyn GeoLocator) -> Vec<(String, usize)> {
+    let mut counts: HashMap<String, usize> = HashMap::new();
+
+    for proxy in proxies {
+        let country = locator
+            .lookup_host(&proxy.host)
+            .and_then(|geo| geo.country_code)
+            .unwrap_or_else(|| UNKNOWN_COUNTRY.to_string());
+        *counts.entry(country).or_default() += 1;
+    }
+
+    let mut counts: Vec<_> = counts.into_iter().collect();
+    counts.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    counts
+}
+
+/// Count proxies per autonomous system (`AS<number> <organization>`), sorted
+/// by count descending
+///
+/// Proxies without ASN data are counted as [`UNKNOWN_COUNTRY`].
+pub fn count_by_network(proxies: &[Proxy], locator: &dyn GeoLocator) -> Vec<(String, usize)> {
+    let mut counts: HashMap<String, usize> = HashMap::new();
+
+    for proxy in proxies {
+        let network = locator
+            .lookup_host(&proxy.host)
+            .and_then(|geo| {
+                let asn = geo.asn?;
+                Some(match geo.as_org {
+                    Some(org) => format!("AS{} {}", asn, org),
+                    None => format!("AS{}", asn),
+                })
+            })
+            .unwrap_or_else(|| UNKNOWN_COUNTRY.to_string());
+        *counts.entry(network).or_default() += 1;
+    }
+
+    let mut counts: Vec<_> = counts.into_iter().collect();
+    counts.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    counts
+}
+
+/// Whether the locator knows the country of `proxy`'s host
+pub fn is_placeable(proxy: &Proxy, locator: &dyn GeoLocator) -> bool {
+    locator
+        .lookup_host(&proxy.host)
+        .is_some_and(|geo| geo.country_code.is_some())
+}
+
+/// Drop results whose proxy can't be placed in a country, returning how many were dropped
+pub fn retain_placeable(results: &mut Vec<ProxyCheckResult>, locator: &dyn GeoLocator) -> usize {
+    let before = results.len();
+    results.retain(|result| is_placeable(&result.proxy, locator));
+    before - results.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proxy::models::ProxyType;
+
+    /// Places 10.0.0.0/8 in the US, 20.0.0.0/8 in DE, and nothing else
+    struct MockLocator;
+
+    impl GeoLocator for MockLocator {
+        fn lookup(&self, ip: IpAddr) -> Option<GeoInfo> {
+            let country = match ip {
+                IpAddr::V4(v4) if v4.octets()[0] == 10 => "US",
+                IpAddr::V4(v4) if v4.octets()[0] == 20 => "DE",
+                _ => return None,
+            };
+            Some(GeoInfo {
+                country_code: Some(country.to_string()),
+                ..Default::default()
+            })
+        }
+    }
+
+    /// Counts how often the wrapped locator is actually queried
+    struct CountingLocator(std::sync::atomic::AtomicUsize);
+
+    impl GeoLocator for CountingLocator {
+        fn lookup(&self, ip: IpAddr) -> Option<GeoInfo> {
+            self.0.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            MockLocator.lookup(ip)
+        }
+    }
+
+    fn counting_locator() -> CachedGeoLocator<CountingLocator> {
+        CachedGeoLocator::new(CountingLocator(Default::default()))
+    }
+
+    fn lookups(locator: &CachedGeoLocator<CountingLocator>) -> usize {
+        locator.inner.0.load(std::sync::atomic::Ordering::SeqCst)
+    }
+
+    fn cache_path() -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("open-proxy-geo-{}.json", uuid::Uuid::new_v4()))
+    }
+
+    fn proxy(host: &str) -> Proxy {
+        Proxy::new(host.to_string(), 8080, ProxyType::Http)
+    }
+
+    /// Value in the MaxMind DB data format, enough to build sample databases
+    enum Mmdb {
+        Str(&'static str),
+        Uint(u32),
+        Map(Vec<(&'static str, Mmdb)>),
+        Array(Vec<Mmdb>),
+    }
+
+    impl Mmdb {
+        fn encode(&self, out: &mut Vec<u8>) {
+            match self {
+                Mmdb::Str(s) => {
+                    // Sizes from 29 take an extra byte holding `size - 29`
+                    match s.len() {
+                        len @ 0..=28 => out.push(2 << 5 | len as u8),
+                        len => out.extend_from_slice(&[2 << 5 | 29, (len - 29) as u8]),
+                    }
+                    out.extend_from_slice(s.as_bytes());
+                }
+                Mmdb::Uint(n) => {
+                    let bytes = n.to_be_bytes();
+                    let skip = bytes.iter().take_while(|b| **b == 0).count();
+                    out.push(6 << 5 | (4 - skip) as u8);
+                    out.extend_from_slice(&bytes[skip..]);
+                }
+                Mmdb::Map(entries) => {
+                    out.push(7 << 5 | entries.len() as u8);
+                    for (key, value) in entries {
+                        Mmdb::Str(key).encode(out);
+                        value.encode(out);
+                    }
+                }
+                Mmdb::Array(items) => {
+                    // Extended type 11, stored as 11 - 7 after the control byte
+                    out.extend_from_slice(&[items.len() as u8, 4]);
+                    for item in items {
+                        item.encode(out);
+                    }
+                }
+            }
+        }
+    }
+
+    /// IPv4 database of `database_type` answering every address with `record`
+    fn sample_mmdb(database_type: &'static str, record: Mmdb) -> Vec<u8> {
+        // One node whose both 24-bit records point at the first data entry
+        let pointer = 1u32 + 16;
+        let mut db = Vec::new();
+        for _ in 0..2 {
+            db.extend_from_slice(&pointer.to_be_bytes()[1..]);
+        }
+        db.extend_from_slice(&[0; 16]);
+        record.encode(&mut db);
+
+        db.extend_from_slice(b"\xab\xcd\xefMaxMind.com");
+        Mmdb::Map(vec![
+            ("binary_format_major_version", Mmdb::Uint(2)),
+            ("binary_format_minor_version", Mmdb::Uint(0)),
+            ("build_epoch", Mmdb::Uint(1_700_000_000)),
+            ("database_type", Mmdb::Str(database_type)),
+            ("description", Mmdb::Map(vec![])),
+            ("ip_version", Mmdb::Uint(4)),
+            ("languages", Mmdb::Array(vec![Mmdb::Str("en")])),
+            ("node_count", Mmdb::Uint(1)),
+            ("record_size", Mmdb::Uint(24)),
+        ])
+        .encode(&mut db);
+        db
+    }
+
+    fn country_record() -> Mmdb {
+        Mmdb::Map(vec![
+            ("iso_code", Mmdb::Str("DE")),
+            ("names", Mmdb::Map(vec![("en", Mmdb::Str("Germany"))])),
+        ])
+    }
+
+    #[test]
+    fn test_mmdb_locator_reads_each_edition() {
+        let ip: IpAddr = "203.0.113.7".parse().unwrap();
+
+        let city = MmdbGeoLocator::from_bytes(sample_mmdb(
+            "GeoLite2-City",
+            Mmdb::Map(vec![
+                (
+                    "city",
+                    Mmdb::Map(vec![(
+                        "names",
+                        Mmdb::Map(vec![("en", Mmdb::Str("Berlin"))]),
+                    )]),
+                ),
+                ("country", country_record()),
+            ]),
+        ))
+        .unwrap();
+        assert_eq!(city.edition(), MmdbEdition::City);
+        assert_eq!(
+            city.lookup(ip),
+            Some(GeoInfo {
+                country_code: Some("DE".to_string()),
+                country_name: Some("Germany".to_string()),
+                city: Some("Berlin".to_string()),
+                ..GeoInfo::default()
+            })
+        );
+
+        let country = MmdbGeoLocator::from_bytes(sample_mmdb(
+            "GeoLite2-Country",
+            Mmdb::Map(vec![("country", country_record())]),
+        ))
+        .unwrap();
+        assert_eq!(country.edition(), MmdbEdition::Country);
+        let geo = country.lookup(ip).unwrap();
+        assert_eq!(geo.country_code.as_deref(), Some("DE"));
+        assert_eq!(geo.country_name.as_deref(), Some("Germany"));
+        assert_eq!(geo.city, None);
+
+        // The ASN database fills in the network on top of the country
+        let asn_db = || {
+            MmdbGeoLocator::from_bytes(sample_mmdb(
+                "GeoLite2-ASN",
+                Mmdb::Map(vec![
+                    ("autonomous_system_number", Mmdb::Uint(64500)),
+                    ("autonomous_system_organization", Mmdb::Str("Example Net")),
+                ]),
+            ))
+            .unwrap()
+        };
+        let combined = country.with_asn(asn_db()).unwrap();
+        let geo = combined.lookup(ip).unwrap();
+        assert_eq!(geo.country_code.as_deref(), Some("DE"));
+        assert_eq!(geo.asn, Some(64500));
+        assert_eq!(geo.as_org.as_deref(), Some("Example Net"));
+        assert_eq!(
+            count_by_network(&[proxy("203.0.113.7"), proxy("host.test")], &combined),
+            vec![
+                ("AS64500 Example Net".to_string(), 1),
+                (UNKNOWN_COUNTRY.to_string(), 1)
+            ]
+        );
+
+        // Only an ASN edition can be the ASN database
+        let not_asn = MmdbGeoLocator::from_bytes(sample_mmdb(
+            "GeoIP2-Country",
+            Mmdb::Map(vec![("country", country_record())]),
+        ))
+        .unwrap();
+        assert!(city.with_asn(not_asn).is_err());
+        assert!(MmdbGeoLocator::from_bytes(sample_mmdb("Mystery-DB", Mmdb::Map(vec![]))).is_err());
+    }
+
+    #[test]
+    fn test_count_by_country() {
+        let proxies = vec![
+            proxy("10.0.0.1"),
+            proxy("10.0.0.2"),
+            proxy("10.1.2.3"),
+            proxy("20.0.0.1"),
+            proxy("30.0.0.1"),
+            proxy("proxy.example.com"),
+        ];
+
+        let counts = count_by_country(&proxies, &MockLocator);
+
+        assert_eq!(
+            counts,
+            vec![
+                ("US".to_string(), 3),
+                (UNKNOWN_COUNTRY.to_string(), 2),
+                ("DE".to_string(), 1),
+            ]
+        );
+    }
+
+    /// Knows 10.0.0.0/8 but without a country, like a database entry
+    /// carrying only a city or continent
+    struct PartialLocator;
+
+    impl GeoLocator for PartialLocator {
+        fn lookup(&self, ip: IpAddr) -> Option<GeoInfo> {
+            match ip {
+                IpAddr::V4(v4) if v4.octets()[0] == 10 => Some(GeoInfo::default()),
+                _ => MockLocator.lookup(ip),
+            }
+        }
+    }
+
+    #[test]
+    fn test_retain_placeable_drops_unplaceable_proxies() {
+        let mut results: Vec<_> = ["10.0.0.1", "20.0.0.1", "30.0.0.1", "proxy.example.com"]
+            .into_iter()
+            .map(|host| ProxyCheckResult::working(proxy(host), 100))
+            .collect();
+
+        assert_eq!(retain_placeable(&mut results, &PartialLocator), 3);
+        let hosts: Vec<_> = results.iter().map(|r| r.proxy.host.as_str()).collect();
+        assert_eq!(hosts, vec!["20.0.0.1"]);
+    }
+
+    #[test]
+    fn test_lookup_host_ignores_hostnames() {
+        assert!(MockLocator.lookup_host("10.0.0.1").is_some());
+        assert!(MockLocator.lookup_host("proxy.example.com").is_none());
+    }
+
+    #[test]
+    fn test_cached_locator_caches_hits_and_misses() {
+        let locator = counting_locator();
+        let us: IpAddr = "10.0.0.1".parse().unwrap();
+        let unknown: IpAddr = "30.0.0.1".parse().unwrap();
+
+        assert!(locator.lookup(us).is_some());
+        assert!(locator.lookup(us).is_some());
+        assert!(locator.lookup(unknown).is_none());
+        assert!(locator.lookup(unknown).is_none());
+
+        assert_eq!(lookups(&locator), 2);
+        assert_eq!(locator.len(), 2);
+    }
+
+    #[test]
+    fn test_geo_cache_round_trip() {
+        let path = cache_path();
+        let first = counting_locator();
+        first.lookup("10.0.0.1".parse().unwrap());
+        first.lookup("20.0.0.1".parse().unwrap());
+        first.lookup("30.0.0.1".parse().unwrap());
+        first.save_cache(&path).unwrap();
+
+        let second = counting_locator();
+        assert_eq!(second.load_cache(&path), 3);
+        assert_eq!(
+            second
+                .lookup("20.0.0.1".parse().unwrap())
+                .and_then(|g| g.country_code),
+            Some("DE".to_string())
+        );
+        assert!(second.lookup("30.0.0.1".parse().unwrap()).is_none());
+        // Everything was served from the loaded cache
+        assert_eq!(lookups(&second), 0);
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_geo_cache_save_keeps_most_recent_entries() {
+        let path = cache_path();
+        let locator = counting_locator().with_max_entries(2);
+        locator.lookup("10.0.0.1".parse().unwrap());
+        locator.lookup("10.0.0.2".parse().unwrap());
+        locator.lookup("10.0.0.3".parse().unwrap());
+        // Touch the first entry again so the second becomes the oldest
+        locator.lookup("10.0.0.1".parse().unwrap());
+        locator.save_cache(&path).unwrap();
+
+        let reloaded = counting_locator();
+        assert_eq!(reloaded.load_cache(&path), 2);
+        reloaded.lookup("10.0.0.1".parse().unwrap());
+        reloaded.lookup("10.0.0.3".parse().unwrap());
+        assert_eq!(lookups(&reloaded), 0);
+        reloaded.lookup("10.0.0.2".parse().unwrap());
+        assert_eq!(lookups(&reloaded), 1);
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_geo_cache_ignores_corrupt_file() {
+        let path = cache_path();
+        fs::write(&path, "{not json").unwrap();
+
+        let locator = counting_locator();
+        assert_eq!(locator.load_cache(&path), 0);
+        assert!(locator.is_empty());
+        assert_eq!(locator.load_cache(cache_path()), 0);
+
+        // Saving replaces the corrupt file with a valid one
+        locator.lookup("10.0.0.1".parse().unwrap());
+        locator.save_cache(&path).unwrap();
+        assert_eq!(counting_locator().load_cache(&path), 1);
+
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -1,14 +1,51 @@
 //! Proxy module for parsing and checking proxies
 //!
 //! This module provides functionality for:
+//! - Crawling proxies from web sources
 //! - Parsing proxies from various formats (IP:PORT, IP:PORT:USER:PASS, etc.)
 //! - Checking proxy validity with multi-threaded support
+//! - Classifying proxy anonymity against header-echo judges
 //! - Saving good and bad proxies to separate files
+//! - Reporting how proxies cluster by subnet and why checks failed
+//! - Geolocating proxy hosts
+//! - Scheduling rechecks based on reliability
+//! - Retrying failed checks and fetches with backoff
+//! - Rotating through recently verified proxies
+//! - Remembering crawled endpoints across runs
+//! - Writing proxies and check results to pluggable output sinks
+//! - Verifying that saved proxies parse back unchanged
 
+pub mod anonymity;
 pub mod checker;
+pub mod crawler;
+pub mod geo;
 pub mod models;
 pub mod parser;
+pub mod pool;
+mod probe;
+pub mod report;
+pub mod retry;
+pub mod roundtrip;
+pub mod scheduler;
+pub mod seen;
+pub mod sink;
+#[cfg(test)]
+mod test_support;
 
-pub use checker::{CheckerConfig, ProxyChecker};
-pub use models::{Proxy, ProxyAuth, ProxyCheckResult, ProxyCheckStatus, ProxyType};
+pub use checker::{
+    CheckProgress, CheckerConfig, ClientCustomizer, MockCheck, ProxyCheck, ProxyChecker, TlsVersion,
+};
+pub use crawler::{CrawlResult, CrawlerConfig, Pagination, ProxyCrawler, ProxySource};
+pub use geo::{CachedGeoLocator, GeoInfo, GeoLocator, MmdbEdition, MmdbGeoLocator};
+pub use models::{
+    AnonymityLevel, AnonymityVerdict, FullFormat, Proxy, ProxyAuth, ProxyCheckResult,
+    ProxyCheckStatus, ProxyType,
+};
 pub use parser::ProxyParser;
+pub use pool::ProxyPool;
+pub use report::{CheckSummary, LogEvent, SampleEstimate, SubnetCount, SubnetReport};
+pub use retry::{Backoff, RetryPolicy};
+pub use roundtrip::{RoundTrip, SaveFormat};
+pub use scheduler::AdaptiveScheduler;
+pub use seen::SeenSet;
+pub use sink::{FileSink, OutputSink};
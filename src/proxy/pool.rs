@@ -0,0 +1,224 @@
+//! Rotating pool of verified proxies with liveness expiry
+
+use crate::proxy::checker::ProxyChecker;
+use crate::proxy::models::{Proxy, ProxyCheckResult};
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::time::Instant;
+
+/// A pooled proxy and when it was last confirmed working
+#[derive(Debug, Clone)]
+struct PoolEntry {
+    proxy: Proxy,
+    last_verified: Instant,
+    /// Already queued for a re-check, so it isn't queued twice
+    recheck_pending: bool,
+}
+
+#[derive(Debug, Default)]
+struct PoolState {
+    entries: Vec<PoolEntry>,
+    cursor: usize,
+    recheck: VecDeque<Proxy>,
+}
+
+/// Round-robin pool handing out proxies that were verified recently
+///
+/// Entries older than the TTL are skipped by [`ProxyPool::next`] and queued
+/// for a re-check; [`ProxyPool::recheck_stale`] runs the queue, refreshing
+/// proxies that still work and dropping the rest.
+#[derive(Debug)]
+pub struct ProxyPool {
+    ttl: Duration,
+    state: Mutex<PoolState>,
+}
+
+impl ProxyPool {
+    /// Create an empty pool whose entries expire `ttl` after their last verification
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            state: Mutex::new(PoolState::default()),
+        }
+    }
+
+    pub fn ttl(&self) -> Duration {
+        self.ttl
+    }
+
+    /// Number of proxies in the pool, stale ones included
+    pub fn len(&self) -> usize {
+        self.lock().entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Add a proxy that was just verified, or refresh it if already pooled
+    pub fn add(&self, proxy: Proxy) {
+        self.add_verified_at(proxy, Instant::now());
+    }
+
+    /// Add a proxy verified at `last_verified`, or refresh it if already pooled
+    pub fn add_verified_at(&self, proxy: Proxy, last_verified: Instant) {
+        let mut state = self.lock();
+        let existing = state
+            .entries
+            .iter_mut()
+            .find(|e| e.proxy.host == proxy.host && e.proxy.port == proxy.port);
+
+        match existing {
+            Some(entry) => {
+                entry.proxy = proxy;
+                entry.last_verified = last_verified;
+                entry.recheck_pending = false;
+            }
+            None => state.entries.push(PoolEntry {
+                proxy,
+                last_verified,
+                recheck_pending: false,
+            }),
+        }
+    }
+
+    /// Add the working proxies from a batch of check results
+    pub fn extend_from_results(&self, results: &[ProxyCheckResult]) {
+        for result in results.iter().filter(|r| r.is_working()) {
+            self.add(result.as_proxy_with_verified_type());
+        }
+    }
+
+    /// Remove a proxy from the pool, returning whether it was pooled
+    pub fn remove(&self, proxy: &Proxy) -> bool {
+        let mut state = self.lock();
+        let before = state.entries.len();
+        state
+            .entries
+            .retain(|e| !(e.proxy.host == proxy.host && e.proxy.port == proxy.port));
+        before != state.entries.len()
+    }
+
+    /// Get the next fresh proxy in round-robin order
+    ///
+    /// Stale entries passed over are queued for a re-check. Returns `None`
+    /// when every pooled proxy is stale.
+    pub fn next(&self) -> Option<Proxy> {
+        let now = Instant::now();
+        let mut state = self.lock();
+        let len = state.entries.len();
+
+        for _ in 0..len {
+            let index = state.cursor % len;
+            state.cursor = (index + 1) % len;
+
+            let entry = &mut state.entries[index];
+            if now.duration_since(entry.last_verified) <= self.ttl {
+                return Some(entry.proxy.clone());
+            }
+            if !entry.recheck_pending {
+                entry.recheck_pending = true;
+                let proxy = entry.proxy.clone();
+                state.recheck.push_back(proxy);
+            }
+        }
+
+        None
+    }
+
+    /// Take the proxies queued for a re-check
+    pub fn take_rechecks(&self) -> Vec<Proxy> {
+        self.lock().recheck.drain(..).collect()
+    }
+
+    /// Re-check queued stale proxies, refreshing working ones and dropping the rest
+    ///
+    /// Returns the number of proxies that were refreshed.
+    pub async fn recheck_stale(&self, checker: &ProxyChecker) -> usize {
+        let stale = self.take_rechecks();
+        if stale.is_empty() {
+            return 0;
+        }
+
+        let mut refreshed = 0;
+        for result in checker.check_proxies(stale).await {
+            if result.is_working() {
+                self.add(result.as_proxy_with_verified_type());
+                refreshed += 1;
+            } else {
+                self.remove(&result.proxy);
+            }
+        }
+        refreshed
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, PoolState> {
+        self.state.lock().expect("Proxy pool poisoned")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proxy::checker::MockCheck;
+    use crate::proxy::models::ProxyType;
+    use std::sync::Arc;
+
+    fn proxy(host: &str) -> Proxy {
+        Proxy::new(host.to_string(), 8080, ProxyType::Http)
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_next_skips_stale_proxies() {
+        let pool = ProxyPool::new(Duration::from_secs(60));
+        pool.add(proxy("10.0.0.1"));
+        tokio::time::advance(Duration::from_secs(30)).await;
+        pool.add(proxy("10.0.0.2"));
+        tokio::time::advance(Duration::from_secs(40)).await;
+
+        // 10.0.0.1 was verified 70s ago, past the TTL
+        assert_eq!(pool.next().unwrap().host, "10.0.0.2");
+        assert_eq!(pool.next().unwrap().host, "10.0.0.2");
+        assert_eq!(pool.take_rechecks(), vec![proxy("10.0.0.1")]);
+        assert_eq!(pool.len(), 2);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_next_rotates_fresh_proxies() {
+        let pool = ProxyPool::new(Duration::from_secs(60));
+        pool.add(proxy("10.0.0.1"));
+        pool.add(proxy("10.0.0.2"));
+
+        let hosts: Vec<_> = (0..4).map(|_| pool.next().unwrap().host).collect();
+        assert_eq!(hosts, vec!["10.0.0.1", "10.0.0.2", "10.0.0.1", "10.0.0.2"]);
+        assert!(pool.take_rechecks().is_empty());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_all_stale_returns_none() {
+        let pool = ProxyPool::new(Duration::from_secs(10));
+        pool.add(proxy("10.0.0.1"));
+        tokio::time::advance(Duration::from_secs(11)).await;
+
+        assert!(pool.next().is_none());
+        // Queued once even when skipped repeatedly
+        assert!(pool.next().is_none());
+        assert_eq!(pool.take_rechecks().len(), 1);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_recheck_stale_refreshes_and_drops() {
+        let pool = ProxyPool::new(Duration::from_secs(10));
+        // MockCheck passes even last octets and fails odd ones
+        pool.add(proxy("10.0.0.2"));
+        pool.add(proxy("10.0.0.3"));
+        tokio::time::advance(Duration::from_secs(11)).await;
+        assert!(pool.next().is_none());
+
+        let checker = ProxyChecker::new().with_check(Arc::new(MockCheck));
+        assert_eq!(pool.recheck_stale(&checker).await, 1);
+        assert_eq!(pool.len(), 1);
+        assert_eq!(pool.next().unwrap().host, "10.0.0.2");
+    }
+}
@@ -0,0 +1,353 @@
+//! Low-level probes that talk to proxies over raw TCP
+//!
+//! Some checks need visibility that an HTTP client hides (for example whether
+//! two requests travelled over the same connection), so they speak HTTP/1.1
+//! to the proxy directly.
+
+use crate::proxy::models::{Proxy, ProxyType};
+use base64::Engine;
+use std::io;
+use std::net::SocketAddrV4;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
+
+/// A parsed HTTP/1.x response
+#[derive(Debug, Clone)]
+pub(crate) struct RawResponse {
+    pub version: String,
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl RawResponse {
+    /// Get the first header value matching `name` (case-insensitive)
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// Whether the peer announced it will close the connection after this response
+    pub fn closes_connection(&self) -> bool {
+        let wants = |value: &str| {
+            ["Connection", "Proxy-Connection"].iter().any(|name| {
+                self.header(name)
+                    .is_some_and(|v| v.to_ascii_lowercase().contains(value))
+            })
+        };
+
+        if self.version == "HTTP/1.0" {
+            !wants("keep-alive")
+        } else {
+            wants("close")
+        }
+    }
+}
+
+/// Build a `Proxy-Authorization` header value for proxies with credentials
+pub(crate) fn proxy_authorization(proxy: &Proxy) -> Option<String> {
+    proxy.auth.as_ref().map(|auth| {
+        let credentials = format!("{}:{}", auth.username, auth.password);
+        format!(
+            "Basic {}",
+            base64::engine::general_purpose::STANDARD.encode(credentials)
+        )
+    })
+}
+
+/// Read the status line and headers of an HTTP/1.x response, leaving the body unread
+pub(crate) async fn read_response_head<R>(reader: &mut BufReader<R>) -> io::Result<RawResponse>
+where
+    R: tokio::io::AsyncRead + Unpin,
+{
+    let mut status_line = String::new();
+    if reader.read_line(&mut status_line).await? == 0 {
+        return Err(io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            "connection closed before response",
+        ));
+    }
+
+    let mut parts = status_line.split_whitespace();
+    let version = parts.next().unwrap_or_default().to_string();
+    let status = parts
+        .next()
+        .and_then(|s| s.parse::<u16>().ok())
+        .ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("invalid status line: {}", status_line.trim()),
+            )
+        })?;
+
+    let mut headers = Vec::new();
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line).await? == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "connection closed while reading headers",
+            ));
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            headers.push((name.trim().to_string(), value.trim().to_string()));
+        }
+    }
+
+    Ok(RawResponse {
+        version,
+        status,
+        headers,
+        body: Vec::new(),
+    })
+}
+
+/// Read a single HTTP/1.x response from the stream
+///
+/// Responses without a length that are delimited by the connection closing
+/// are read until EOF.
+pub(crate) async fn read_response<R>(reader: &mut BufReader<R>) -> io::Result<RawResponse>
+where
+    R: tokio::io::AsyncRead + Unpin,
+{
+    let mut response = read_response_head(reader).await?;
+    let status = response.status;
+
+    if status < 200 || status == 204 || status == 304 {
+        return Ok(response);
+    }
+
+    let chunked = response
+        .header("Transfer-Encoding")
+        .is_some_and(|v| v.to_ascii_lowercase().contains("chunked"));
+    let content_length = response
+        .header("Content-Length")
+        .and_then(|v| v.parse::<usize>().ok());
+
+    if chunked {
+        response.body = read_chunked_body(reader).await?;
+    } else if let Some(len) = content_length {
+        let mut body = vec![0; len];
+        reader.read_exact(&mut body).await?;
+        response.body = body;
+    } else {
+        reader.read_to_end(&mut response.body).await?;
+    }
+
+    Ok(response)
+}
+
+async fn read_chunked_body<R>(reader: &mut BufReader<R>) -> io::Result<Vec<u8>>
+where
+    R: tokio::io::AsyncRead + Unpin,
+{
+    let mut body = Vec::new();
+    loop {
+        let mut size_line = String::new();
+        reader.read_line(&mut size_line).await?;
+        let size_str = size_line.trim().split(';').next().unwrap_or_default();
+        let size = usize::from_str_radix(size_str, 16)
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "invalid chunk size"))?;
+
+        if size == 0 {
+            // Skip trailers up to the final empty line
+            loop {
+                let mut line = String::new();
+                if reader.read_line(&mut line).await? == 0 || line.trim().is_empty() {
+                    return Ok(body);
+                }
+            }
+        }
+
+        let mut chunk = vec![0; size + 2];
+        reader.read_exact(&mut chunk).await?;
+        chunk.truncate(size);
+        body.extend_from_slice(&chunk);
+    }
+}
+
+/// Check whether an HTTP proxy keeps the client connection open
+///
+/// Sends two requests for `url` over the same TCP connection, `gap` apart.
+/// Returns `None` when the check doesn't apply: SOCKS proxies and `https://`
+/// targets are tunnelled end-to-end, so persistence isn't up to the proxy.
+pub(crate) async fn check_keepalive(proxy: &Proxy, url: &str, gap: Duration) -> Option<bool> {
+    if !matches!(proxy.proxy_type, ProxyType::Http | ProxyType::Https) {
+        return None;
+    }
+
+    let target = reqwest::Url::parse(url).ok()?;
+    if target.scheme() != "http" {
+        return None;
+    }
+    let host = target.host_str()?;
+    let host_header = match target.port() {
+        Some(port) => format!("{}:{}", host, port),
+        None => host.to_string(),
+    };
+
+    let mut request = format!(
+        "GET {} HTTP/1.1\r\nHost: {}\r\nConnection: keep-alive\r\nProxy-Connection: keep-alive\r\n",
+        target, host_header
+    );
+    if let Some(auth) = proxy_authorization(proxy) {
+        request.push_str(&format!("Proxy-Authorization: {}\r\n", auth));
+    }
+    request.push_str("\r\n");
+
+    let stream = match TcpStream::connect((proxy.host.as_str(), proxy.port)).await {
+        Ok(stream) => stream,
+        Err(_) => return Some(false),
+    };
+    let mut stream = BufReader::new(stream);
+
+    for attempt in 0..2 {
+        if attempt > 0 {
+            tokio::time::sleep(gap).await;
+        }
+
+        if stream
+            .get_mut()
+            .write_all(request.as_bytes())
+            .await
+            .is_err()
+        {
+            return Some(false);
+        }
+
+        match read_response(&mut stream).await {
+            Ok(response) if (200..300).contains(&response.status) => {
+                if attempt == 0 && response.closes_connection() {
+                    return Some(false);
+                }
+            }
+            _ => return Some(false),
+        }
+    }
+
+    Some(true)
+}
+
+/// Check whether an HTTP proxy opens a `CONNECT` tunnel to `authority` (`host:port`)
+pub(crate) async fn check_connect(proxy: &Proxy, authority: &str) -> bool {
+    let mut request = format!(
+        "CONNECT {authority} HTTP/1.1\r\nHost: {authority}\r\nProxy-Connection: keep-alive\r\n"
+    );
+    if let Some(auth) = proxy_authorization(proxy) {
+        request.push_str(&format!("Proxy-Authorization: {}\r\n", auth));
+    }
+    request.push_str("\r\n");
+
+    let Ok(mut stream) = TcpStream::connect((proxy.host.as_str(), proxy.port)).await else {
+        return false;
+    };
+    if stream.write_all(request.as_bytes()).await.is_err() {
+        return false;
+    }
+
+    // A successful CONNECT reply has no body; the tunnel starts right after the headers
+    match read_response_head(&mut BufReader::new(stream)).await {
+        Ok(response) => (200..300).contains(&response.status),
+        Err(_) => false,
+    }
+}
+
+/// Check whether the proxy answers a SOCKS5 greeting with an acceptable method
+pub(crate) async fn check_socks5(proxy: &Proxy) -> bool {
+    // Offer "no authentication", plus username/password when we have credentials
+    let greeting: &[u8] = if proxy.auth.is_some() {
+        &[0x05, 0x02, 0x00, 0x02]
+    } else {
+        &[0x05, 0x01, 0x00]
+    };
+
+    let Ok(mut stream) = TcpStream::connect((proxy.host.as_str(), proxy.port)).await else {
+        return false;
+    };
+    if stream.write_all(greeting).await.is_err() {
+        return false;
+    }
+
+    let mut reply = [0u8; 2];
+    match stream.read_exact(&mut reply).await {
+        Ok(_) => reply[0] == 0x05 && greeting[2..].contains(&reply[1]),
+        Err(_) => false,
+    }
+}
+
+/// Check whether the proxy grants a SOCKS4 connect request to `target`
+pub(crate) async fn check_socks4(proxy: &Proxy, target: SocketAddrV4) -> bool {
+    let mut request = vec![0x04, 0x01];
+    request.extend_from_slice(&target.port().to_be_bytes());
+    request.extend_from_slice(&target.ip().octets());
+    if let Some(ref auth) = proxy.auth {
+        request.extend_from_slice(auth.username.as_bytes());
+    }
+    request.push(0x00);
+
+    let Ok(mut stream) = TcpStream::connect((proxy.host.as_str(), proxy.port)).await else {
+        return false;
+    };
+    if stream.write_all(&request).await.is_err() {
+        return false;
+    }
+
+    let mut reply = [0u8; 8];
+    match stream.read_exact(&mut reply).await {
+        Ok(_) => reply[0] == 0x00 && reply[1] == 0x5A,
+        Err(_) => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_read_response_content_length() {
+        let raw = b"HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\nhello";
+        let mut reader = BufReader::new(&raw[..]);
+        let response = read_response(&mut reader).await.unwrap();
+        assert_eq!(response.status, 200);
+        assert_eq!(response.body, b"hello");
+        assert!(!response.closes_connection());
+    }
+
+    #[tokio::test]
+    async fn test_read_response_chunked() {
+        let raw = b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n3\r\nabc\r\n2\r\nde\r\n0\r\n\r\n";
+        let mut reader = BufReader::new(&raw[..]);
+        let response = read_response(&mut reader).await.unwrap();
+        assert_eq!(response.body, b"abcde");
+    }
+
+    #[tokio::test]
+    async fn test_closes_connection() {
+        let raw = b"HTTP/1.1 200 OK\r\nConnection: close\r\nContent-Length: 0\r\n\r\n";
+        let response = read_response(&mut BufReader::new(&raw[..])).await.unwrap();
+        assert!(response.closes_connection());
+
+        let raw = b"HTTP/1.0 200 OK\r\nContent-Length: 0\r\n\r\n";
+        let response = read_response(&mut BufReader::new(&raw[..])).await.unwrap();
+        assert!(response.closes_connection());
+    }
+
+    #[tokio::test]
+    async fn test_read_response_head_leaves_tunnel_unread() {
+        let raw = b"HTTP/1.1 200 Connection established\r\n\r\ntunnel bytes";
+        let mut reader = BufReader::new(&raw[..]);
+        let response = read_response_head(&mut reader).await.unwrap();
+        assert_eq!(response.status, 200);
+
+        let mut rest = Vec::new();
+        reader.read_to_end(&mut rest).await.unwrap();
+        assert_eq!(rest, b"tunnel bytes");
+    }
+}
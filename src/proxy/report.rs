@@ -0,0 +1,675 @@
+//! Reports summarizing where proxies cluster and how checks went
+
+use crate::proxy::checker::CAPTIVE_PORTAL_ERROR;
+use crate::proxy::crawler::CrawlResult;
+use crate::proxy::models::{
+    AnonymityLevel, AnonymityVerdict, Proxy, ProxyCheckResult, ProxyCheckStatus,
+};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+/// Number of proxies found in a single subnet
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SubnetCount {
+    /// Subnet in CIDR notation (e.g. `192.168.1.0/24`)
+    pub subnet: String,
+    /// Number of proxies inside the subnet
+    pub count: usize,
+}
+
+/// Proxies grouped by subnet, each group sorted by count descending
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SubnetReport {
+    /// IPv4 proxies grouped by /24
+    pub ipv4_24: Vec<SubnetCount>,
+    /// IPv4 proxies grouped by /16
+    pub ipv4_16: Vec<SubnetCount>,
+    /// IPv6 proxies grouped by /64
+    pub ipv6_64: Vec<SubnetCount>,
+    /// Number of proxies whose host is not an IP address
+    pub skipped: usize,
+}
+
+impl SubnetReport {
+    /// Build a report from a list of proxies
+    pub fn from_proxies<'a, I>(proxies: I) -> Self
+    where
+        I: IntoIterator<Item = &'a Proxy>,
+    {
+        let mut v4_24: HashMap<Ipv4Addr, usize> = HashMap::new();
+        let mut v4_16: HashMap<Ipv4Addr, usize> = HashMap::new();
+        let mut v6_64: HashMap<Ipv6Addr, usize> = HashMap::new();
+        let mut skipped = 0;
+
+        for proxy in proxies {
+            match proxy.host.parse::<IpAddr>() {
+                Ok(IpAddr::V4(ip)) => {
+                    *v4_24.entry(mask_v4(ip, 24)).or_default() += 1;
+                    *v4_16.entry(mask_v4(ip, 16)).or_default() += 1;
+                }
+                Ok(IpAddr::V6(ip)) => {
+                    *v6_64.entry(mask_v6(ip, 64)).or_default() += 1;
+                }
+                Err(_) => skipped += 1,
+            }
+        }
+
+        Self {
+            ipv4_24: sorted_counts(v4_24, 24),
+            ipv4_16: sorted_counts(v4_16, 16),
+            ipv6_64: sorted_counts(v6_64, 64),
+            skipped,
+        }
+    }
+
+    /// Build a report from check results, counting only working proxies
+    pub fn from_results(results: &[ProxyCheckResult]) -> Self {
+        Self::from_proxies(results.iter().filter(|r| r.is_working()).map(|r| &r.proxy))
+    }
+}
+
+fn mask_v4(ip: Ipv4Addr, prefix: u32) -> Ipv4Addr {
+    Ipv4Addr::from(u32::from(ip) & (u32::MAX << (32 - prefix)))
+}
+
+fn mask_v6(ip: Ipv6Addr, prefix: u32) -> Ipv6Addr {
+    Ipv6Addr::from(u128::from(ip) & (u128::MAX << (128 - prefix)))
+}
+
+fn sorted_counts<A: std::fmt::Display>(counts: HashMap<A, usize>, prefix: u32) -> Vec<SubnetCount> {
+    let mut counts: Vec<SubnetCount> = counts
+        .into_iter()
+        .map(|(network, count)| SubnetCount {
+            subnet: format!("{}/{}", network, prefix),
+            count,
+        })
+        .collect();
+    // Sort by count descending, then by subnet for a stable output
+    counts.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.subnet.cmp(&b.subnet)));
+    counts
+}
+
+/// Totals for a batch of check results
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CheckSummary {
+    pub total: usize,
+    pub working: usize,
+    pub failed: usize,
+    /// Checks the judge failed, counted neither as working nor as failed
+    pub judge_errors: usize,
+    /// Mean response time of working proxies
+    pub avg_response_time_ms: Option<u64>,
+    /// Median response time of working proxies
+    pub median_response_time_ms: Option<u64>,
+    /// Failed and timed-out checks counted per category (see [`classify_failure`])
+    pub failure_breakdown: HashMap<String, usize>,
+}
+
+impl CheckSummary {
+    pub fn from_results(results: &[ProxyCheckResult]) -> Self {
+        let mut summary = Self {
+            total: results.len(),
+            ..Self::default()
+        };
+        let mut response_times = Vec::new();
+
+        for result in results {
+            if result.is_judge_error() {
+                summary.judge_errors += 1;
+                continue;
+            }
+            match classify_failure(&result.status) {
+                None => {
+                    summary.working += 1;
+                    response_times.extend(result.response_time_ms);
+                }
+                Some(category) => {
+                    summary.failed += 1;
+                    *summary.failure_breakdown.entry(category).or_default() += 1;
+                }
+            }
+        }
+
+        if !response_times.is_empty() {
+            summary.avg_response_time_ms =
+                Some(response_times.iter().sum::<u64>() / response_times.len() as u64);
+        }
+        response_times.sort_unstable();
+        summary.median_response_time_ms = percentile(&response_times, 50.0);
+        summary
+    }
+
+    /// Failure categories sorted by count descending, then name
+    pub fn sorted_failures(&self) -> Vec<(&str, usize)> {
+        let mut failures: Vec<_> = self
+            .failure_breakdown
+            .iter()
+            .map(|(category, &count)| (category.as_str(), count))
+            .collect();
+        failures.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+        failures
+    }
+}
+
+/// Nearest-rank percentile (0-100) of ascending `sorted` values
+///
+/// Always returns one of the values, so the 50th percentile of an even-sized
+/// set is the lower of the two middle values.
+pub fn percentile(sorted: &[u64], percentile: f64) -> Option<u64> {
+    if sorted.is_empty() {
+        return None;
+    }
+    let rank = (percentile.clamp(0.0, 100.0) / 100.0 * sorted.len() as f64).ceil() as usize;
+    Some(sorted[rank.clamp(1, sorted.len()) - 1])
+}
+
+/// Keep only working results at or below the median response time
+///
+/// Returns the median, or `None` (leaving `results` untouched) when there are
+/// fewer than two timed working results to compare.
+pub fn retain_faster_than_median(results: &mut Vec<ProxyCheckResult>) -> Option<u64> {
+    let timed = results
+        .iter()
+        .filter(|r| r.is_working() && r.response_time_ms.is_some())
+        .count();
+    if timed < 2 {
+        return None;
+    }
+    let median = CheckSummary::from_results(results).median_response_time_ms?;
+    results.retain(|r| r.is_working() && r.response_time_ms.is_some_and(|ms| ms <= median));
+    Some(median)
+}
+
+/// Bucket a failed check by its cause, returning `None` for working proxies
+///
+/// Categories are `timeout`, `refused`, `dns`, `tls`, `http-<status>` (e.g.
+/// `http-403`), `captive-portal`, `judge` and `other`. Errors are matched on
+/// their message, so new error wording from the HTTP stack may end up in
+/// `other`.
+pub fn classify_failure(status: &ProxyCheckStatus) -> Option<String> {
+    let message = match status {
+        ProxyCheckStatus::Working => return None,
+        ProxyCheckStatus::Timeout => return Some("timeout".to_string()),
+        ProxyCheckStatus::JudgeError(_) => return Some("judge".to_string()),
+        ProxyCheckStatus::Failed(message) => message.to_lowercase(),
+    };
+
+    if let Some(rest) = message.strip_prefix("http status: ") {
+        if let Some(code) = rest.split_whitespace().next() {
+            return Some(format!("http-{}", code));
+        }
+    }
+
+    let category = if message.contains("timed out") || message.contains("timeout") {
+        "timeout"
+    } else if message == CAPTIVE_PORTAL_ERROR {
+        "captive-portal"
+    } else if message.contains("refused") {
+        "refused"
+    } else if message.contains("dns error")
+        || message.contains("failed to lookup address")
+        || message.contains("name or service not known")
+        || message.contains("no such host")
+    {
+        "dns"
+    } else if message.contains("tls")
+        || message.contains("ssl")
+        || message.contains("certificate")
+        || message.contains("handshake")
+    {
+        "tls"
+    } else {
+        "other"
+    };
+    Some(category.to_string())
+}
+
+/// Suggest what to do about a crawl error, or `None` when there's nothing specific to say
+///
+/// The error is bucketed like a check failure (see [`classify_failure`]), so
+/// the same message matching applies.
+pub fn crawl_error_hint(error: &str) -> Option<&'static str> {
+    let category = classify_failure(&ProxyCheckStatus::Failed(error.to_string()))?;
+    let hint = match category.as_str() {
+        "dns" => "DNS lookup failed; check the URL's host name",
+        "tls" => "TLS handshake failed; the site's certificate may be invalid or expired",
+        "timeout" => "the site didn't answer in time; try a longer --timeout or more --retries",
+        "refused" => "nothing is listening there; check the URL's host and port",
+        "http-401" | "http-403" => {
+            "the site may be blocking datacenter IPs or scripted clients; try crawling through a proxy (set HTTPS_PROXY)"
+        }
+        "http-404" | "http-410" => "the list isn't at this URL anymore; check the source URL",
+        "http-429" => "the site is rate limiting; crawl it less often or with a lower --concurrency",
+        category if category.starts_with("http-5") => {
+            "the site is having server trouble; try again later"
+        }
+        _ => return None,
+    };
+    Some(hint)
+}
+
+/// Quality of a full list estimated from checking a random sample of it
+#[derive(Debug, Clone, PartialEq)]
+pub struct SampleEstimate {
+    /// Number of proxies in the full list
+    pub population: usize,
+    /// Number of proxies checked
+    pub sample_size: usize,
+    /// Number of sampled proxies that worked
+    pub working: usize,
+}
+
+impl SampleEstimate {
+    /// Sample sizes below this give too rough an estimate to rely on
+    pub const MIN_RELIABLE_SAMPLE: usize = 30;
+
+    pub fn new(population: usize, sample_size: usize, working: usize) -> Self {
+        Self {
+            population,
+            sample_size,
+            working,
+        }
+    }
+
+    /// Fraction of the sample that worked
+    pub fn success_rate(&self) -> f64 {
+        if self.sample_size == 0 {
+            0.0
+        } else {
+            self.working as f64 / self.sample_size as f64
+        }
+    }
+
+    /// Half-width of the 95% confidence interval around [`SampleEstimate::success_rate`]
+    ///
+    /// Normal approximation with a finite population correction, so checking
+    /// the whole list gives a margin of zero.
+    pub fn margin_of_error(&self) -> f64 {
+        if self.sample_size == 0 || self.population <= 1 {
+            return 0.0;
+        }
+        let p = self.success_rate();
+        let n = self.sample_size as f64;
+        let big_n = self.population as f64;
+        let correction = ((big_n - n) / (big_n - 1.0)).max(0.0).sqrt();
+        1.96 * (p * (1.0 - p) / n).sqrt() * correction
+    }
+
+    /// Expected number of working proxies in the full list
+    pub fn estimated_working(&self) -> usize {
+        (self.success_rate() * self.population as f64).round() as usize
+    }
+
+    pub fn is_reliable(&self) -> bool {
+        self.sample_size >= Self::MIN_RELIABLE_SAMPLE
+    }
+}
+
+/// A structured event printed in `--json-logs` mode
+///
+/// Serialized as a single JSON object whose `type` field names the variant
+/// (`check_result`, `crawl_result`, `proxy` or `summary`). Proxies are
+/// written as `host:port`, without credentials.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum LogEvent {
+    CheckResult {
+        proxy: String,
+        proxy_type: String,
+        working: bool,
+        response_time_ms: Option<u64>,
+        error: Option<String>,
+        /// Failure category from [`classify_failure`]
+        failure: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        anonymity: Option<AnonymityLevel>,
+        /// Verdict of each anonymity judge
+        #[serde(skip_serializing_if = "Vec::is_empty")]
+        judge_anonymity: Vec<AnonymityVerdict>,
+    },
+    CrawlResult {
+        source: String,
+        url: String,
+        proxies: usize,
+        /// Proxies not already seen from earlier sources
+        new: usize,
+        error: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        warning: Option<String>,
+        /// Suggestion for fixing `error` (see [`crawl_error_hint`])
+        #[serde(skip_serializing_if = "Option::is_none")]
+        hint: Option<&'static str>,
+        tripped: bool,
+    },
+    /// A proxy emitted as command output (e.g. by `crawl` without `-o`)
+    Proxy { proxy: String, proxy_type: String },
+    Summary {
+        total: usize,
+        working: usize,
+        failed: usize,
+        judge_errors: usize,
+        avg_response_time_ms: Option<u64>,
+        median_response_time_ms: Option<u64>,
+        failure_breakdown: HashMap<String, usize>,
+    },
+}
+
+impl LogEvent {
+    pub fn check_result(result: &ProxyCheckResult) -> Self {
+        let error = match &result.status {
+            ProxyCheckStatus::Working => None,
+            ProxyCheckStatus::Failed(error) => Some(error.clone()),
+            ProxyCheckStatus::Timeout => Some("timeout".to_string()),
+            ProxyCheckStatus::JudgeError(error) => Some(error.clone()),
+        };
+        LogEvent::CheckResult {
+            proxy: result.proxy.to_simple_string(),
+            proxy_type: result.proxy.proxy_type.to_string(),
+            working: result.is_working(),
+            response_time_ms: result.response_time_ms,
+            error,
+            failure: classify_failure(&result.status),
+            anonymity: result.anonymity,
+            judge_anonymity: result.judge_anonymity.clone(),
+        }
+    }
+
+    pub fn crawl_result(result: &CrawlResult, new: usize) -> Self {
+        LogEvent::CrawlResult {
+            source: result.source.name.clone(),
+            url: result.source.url.clone(),
+            proxies: result.proxies.len(),
+            new,
+            error: result.error.clone(),
+            warning: result.warning.clone(),
+            hint: result.error.as_deref().and_then(crawl_error_hint),
+            tripped: result.tripped,
+        }
+    }
+
+    pub fn proxy(proxy: &Proxy) -> Self {
+        LogEvent::Proxy {
+            proxy: proxy.to_simple_string(),
+            proxy_type: proxy.proxy_type.to_string(),
+        }
+    }
+
+    pub fn summary(summary: &CheckSummary) -> Self {
+        LogEvent::Summary {
+            total: summary.total,
+            working: summary.working,
+            failed: summary.failed,
+            judge_errors: summary.judge_errors,
+            avg_response_time_ms: summary.avg_response_time_ms,
+            median_response_time_ms: summary.median_response_time_ms,
+            failure_breakdown: summary.failure_breakdown.clone(),
+        }
+    }
+
+    /// Serialize the event as one line of newline-delimited JSON (without the newline)
+    pub fn to_json_line(&self) -> String {
+        serde_json::to_string(self).expect("Log events always serialize")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proxy::models::ProxyType;
+
+    fn proxy(host: &str) -> Proxy {
+        Proxy::new(host.to_string(), 8080, ProxyType::Http)
+    }
+
+    #[test]
+    fn test_subnet_grouping_counts() {
+        let proxies = vec![
+            proxy("10.0.1.1"),
+            proxy("10.0.1.2"),
+            proxy("10.0.1.3"),
+            proxy("10.0.2.1"),
+            proxy("192.168.5.5"),
+            proxy("2001:db8:1:2::1"),
+            proxy("2001:db8:1:2::ffff"),
+            proxy("2001:db8:1:3::1"),
+            proxy("proxy.example.com"),
+        ];
+
+        let report = SubnetReport::from_proxies(&proxies);
+
+        assert_eq!(
+            report.ipv4_24,
+            vec![
+                SubnetCount {
+                    subnet: "10.0.1.0/24".to_string(),
+                    count: 3
+                },
+                SubnetCount {
+                    subnet: "10.0.2.0/24".to_string(),
+                    count: 1
+                },
+                SubnetCount {
+                    subnet: "192.168.5.0/24".to_string(),
+                    count: 1
+                },
+            ]
+        );
+        assert_eq!(
+            report.ipv4_16,
+            vec![
+                SubnetCount {
+                    subnet: "10.0.0.0/16".to_string(),
+                    count: 4
+                },
+                SubnetCount {
+                    subnet: "192.168.0.0/16".to_string(),
+                    count: 1
+                },
+            ]
+        );
+        assert_eq!(
+            report.ipv6_64,
+            vec![
+                SubnetCount {
+                    subnet: "2001:db8:1:2::/64".to_string(),
+                    count: 2
+                },
+                SubnetCount {
+                    subnet: "2001:db8:1:3::/64".to_string(),
+                    count: 1
+                },
+            ]
+        );
+        assert_eq!(report.skipped, 1);
+    }
+
+    #[test]
+    fn test_subnet_report_from_results_counts_only_working() {
+        let results = vec![
+            ProxyCheckResult::working(proxy("10.0.1.1"), 100),
+            ProxyCheckResult::working(proxy("10.0.1.2"), 120),
+            ProxyCheckResult::failed(proxy("10.0.1.3"), "refused".to_string()),
+            ProxyCheckResult::timeout(proxy("10.0.2.1")),
+        ];
+
+        let report = SubnetReport::from_results(&results);
+
+        assert_eq!(report.ipv4_24.len(), 1);
+        assert_eq!(report.ipv4_24[0].subnet, "10.0.1.0/24");
+        assert_eq!(report.ipv4_24[0].count, 2);
+    }
+
+    fn failed(message: &str) -> ProxyCheckStatus {
+        ProxyCheckStatus::Failed(message.to_string())
+    }
+
+    #[test]
+    fn test_classify_failure() {
+        assert_eq!(classify_failure(&ProxyCheckStatus::Working), None);
+        let cases = [
+            (ProxyCheckStatus::Timeout, "timeout"),
+            (
+                failed("error sending request for url (http://httpbin.org/ip): operation timed out"),
+                "timeout",
+            ),
+            (
+                failed("error sending request for url (http://httpbin.org/ip): error trying to connect: tcp connect error: Connection refused (os error 111)"),
+                "refused",
+            ),
+            (
+                failed("error trying to connect: dns error: failed to lookup address information: Name or service not known"),
+                "dns",
+            ),
+            (
+                failed("error trying to connect: invalid peer certificate: UnknownIssuer"),
+                "tls",
+            ),
+            (failed("HTTP status: 403 Forbidden"), "http-403"),
+            (failed("HTTP status: 502 Bad Gateway"), "http-502"),
+            (failed("captive portal"), "captive-portal"),
+            (failed("socks connect error: general SOCKS server failure"), "other"),
+        ];
+
+        for (status, expected) in cases {
+            assert_eq!(
+                classify_failure(&status).as_deref(),
+                Some(expected),
+                "{:?}",
+                status
+            );
+        }
+    }
+
+    #[test]
+    fn test_crawl_error_hint() {
+        let cases = [
+            (
+                "error sending request for url (https://lists.example/proxies.txt): error trying to connect: dns error: failed to lookup address information: Name or service not known",
+                Some("DNS lookup failed; check the URL's host name"),
+            ),
+            (
+                "error sending request for url (https://lists.example/proxies.txt): error trying to connect: invalid peer certificate: Expired",
+                Some("TLS handshake failed; the site's certificate may be invalid or expired"),
+            ),
+            (
+                "HTTP status: 403 Forbidden",
+                Some("the site may be blocking datacenter IPs or scripted clients; try crawling through a proxy (set HTTPS_PROXY)"),
+            ),
+            (
+                "HTTP status: 404 Not Found",
+                Some("the list isn't at this URL anymore; check the source URL"),
+            ),
+            (
+                "HTTP status: 429 Too Many Requests",
+                Some("the site is rate limiting; crawl it less often or with a lower --concurrency"),
+            ),
+            (
+                "HTTP status: 503 Service Unavailable",
+                Some("the site is having server trouble; try again later"),
+            ),
+            (
+                "error sending request for url (https://lists.example/proxies.txt): operation timed out",
+                Some("the site didn't answer in time; try a longer --timeout or more --retries"),
+            ),
+            ("skipped: too many consecutive failures", None),
+        ];
+
+        for (error, expected) in cases {
+            assert_eq!(crawl_error_hint(error), expected, "{}", error);
+        }
+    }
+
+    #[test]
+    fn test_check_summary_failure_breakdown() {
+        let proxy = Proxy::new("10.0.0.1".to_string(), 8080, ProxyType::Http);
+        let results = vec![
+            ProxyCheckResult::working(proxy.clone(), 100),
+            ProxyCheckResult::working(proxy.clone(), 300),
+            ProxyCheckResult::timeout(proxy.clone()),
+            ProxyCheckResult::timeout(proxy.clone()),
+            ProxyCheckResult::failed(proxy.clone(), "Connection refused".to_string()),
+            ProxyCheckResult::failed(proxy.clone(), "HTTP status: 403 Forbidden".to_string()),
+            ProxyCheckResult::judge_error(
+                proxy.clone(),
+                "Judge returned HTTP status: 429 Too Many Requests".to_string(),
+            ),
+        ];
+
+        let summary = CheckSummary::from_results(&results);
+        assert_eq!(summary.total, 7);
+        assert_eq!(summary.working, 2);
+        assert_eq!(summary.failed, 4);
+        assert_eq!(summary.judge_errors, 1);
+        assert_eq!(summary.avg_response_time_ms, Some(200));
+        assert_eq!(
+            summary.sorted_failures(),
+            vec![("timeout", 2), ("http-403", 1), ("refused", 1)]
+        );
+    }
+
+    #[test]
+    fn test_percentile_nearest_rank() {
+        let sorted = [100, 200, 300, 400, 500];
+        assert_eq!(percentile(&sorted, 50.0), Some(300));
+        assert_eq!(percentile(&sorted, 95.0), Some(500));
+        assert_eq!(percentile(&sorted, 0.0), Some(100));
+        assert_eq!(percentile(&[100, 200, 300, 400], 50.0), Some(200));
+        assert_eq!(percentile(&[], 50.0), None);
+    }
+
+    #[test]
+    fn test_retain_faster_than_median() {
+        let mut results: Vec<_> = [340, 120, 900, 120, 450, 200]
+            .into_iter()
+            .enumerate()
+            .map(|(i, ms)| ProxyCheckResult::working(proxy(&format!("10.0.0.{}", i)), ms))
+            .collect();
+        results.push(ProxyCheckResult::timeout(proxy("10.0.1.1")));
+
+        assert_eq!(retain_faster_than_median(&mut results), Some(200));
+        let mut kept: Vec<_> = results.iter().map(|r| r.proxy.host.as_str()).collect();
+        kept.sort();
+        assert_eq!(kept, vec!["10.0.0.1", "10.0.0.3", "10.0.0.5"]);
+
+        // A single working proxy has nothing to be compared against
+        let mut single = vec![ProxyCheckResult::working(proxy("10.0.0.1"), 500)];
+        assert_eq!(retain_faster_than_median(&mut single), None);
+        assert_eq!(single.len(), 1);
+    }
+
+    #[test]
+    fn test_log_events_carry_type() {
+        let results = vec![
+            ProxyCheckResult::working(proxy("10.0.0.1"), 120),
+            ProxyCheckResult::timeout(proxy("10.0.0.2")),
+        ];
+
+        let line = LogEvent::check_result(&results[1]).to_json_line();
+        let value: serde_json::Value = serde_json::from_str(&line).unwrap();
+        assert_eq!(value["type"], "check_result");
+        assert_eq!(value["proxy"], "10.0.0.2:8080");
+        assert_eq!(value["working"], false);
+        assert_eq!(value["failure"], "timeout");
+
+        let line = LogEvent::summary(&CheckSummary::from_results(&results)).to_json_line();
+        let value: serde_json::Value = serde_json::from_str(&line).unwrap();
+        assert_eq!(value["type"], "summary");
+        assert_eq!(value["working"], 1);
+        assert_eq!(value["failure_breakdown"]["timeout"], 1);
+        assert!(!line.contains('\n'));
+    }
+
+    #[test]
+    fn test_sample_estimate() {
+        let estimate = SampleEstimate::new(10_000, 100, 25);
+        assert_eq!(estimate.success_rate(), 0.25);
+        assert_eq!(estimate.estimated_working(), 2500);
+        assert!((estimate.margin_of_error() - 0.0847).abs() < 0.001);
+        assert!(estimate.is_reliable());
+
+        // Checking everything leaves no uncertainty
+        assert_eq!(SampleEstimate::new(50, 50, 10).margin_of_error(), 0.0);
+        assert!(!SampleEstimate::new(1000, 10, 5).is_reliable());
+    }
+}
@@ -0,0 +1,153 @@
+//! Retry policy shared by the checker and the crawler
+
+use rand::Rng;
+use std::time::Duration;
+
+/// How the delay grows between attempts
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Backoff {
+    /// Wait `base_delay` before every retry
+    #[default]
+    Fixed,
+    /// Double the delay after every failed attempt
+    Exponential,
+}
+
+/// When and how long to wait before retrying a failed operation
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Total attempts, the first one included (1 disables retries)
+    pub max_attempts: usize,
+    /// Delay before the first retry
+    pub base_delay: Duration,
+    pub backoff: Backoff,
+    /// Randomize each delay between half and all of its value, so many
+    /// clients retrying at once don't stay in lockstep
+    pub jitter: bool,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self::none()
+    }
+}
+
+impl RetryPolicy {
+    /// A single attempt without retries
+    pub fn none() -> Self {
+        Self::fixed(1, Duration::ZERO)
+    }
+
+    /// Retry after the same delay every time
+    pub fn fixed(max_attempts: usize, delay: Duration) -> Self {
+        Self {
+            max_attempts: max_attempts.max(1),
+            base_delay: delay,
+            backoff: Backoff::Fixed,
+            jitter: false,
+        }
+    }
+
+    /// Retry after `base_delay`, doubling the delay after each further failure
+    pub fn exponential(max_attempts: usize, base_delay: Duration) -> Self {
+        Self {
+            backoff: Backoff::Exponential,
+            ..Self::fixed(max_attempts, base_delay)
+        }
+    }
+
+    pub fn with_jitter(mut self, jitter: bool) -> Self {
+        self.jitter = jitter;
+        self
+    }
+
+    /// Number of retries after the first attempt
+    pub fn retries(&self) -> usize {
+        self.max_attempts - 1
+    }
+
+    /// Whether another attempt is allowed after `attempt` attempts failed
+    pub fn should_retry(&self, attempt: usize) -> bool {
+        attempt < self.max_attempts
+    }
+
+    /// Delay to wait after `attempt` attempts failed (1 for the first failure)
+    pub fn next_delay(&self, attempt: usize) -> Duration {
+        let delay = match self.backoff {
+            Backoff::Fixed => self.base_delay,
+            Backoff::Exponential => {
+                let exponent = attempt.saturating_sub(1).min(31) as u32;
+                self.base_delay.saturating_mul(1 << exponent)
+            }
+        };
+
+        if self.jitter && !delay.is_zero() {
+            let half = delay / 2;
+            half + rand::thread_rng().gen_range(Duration::ZERO..=delay - half)
+        } else {
+            delay
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn delays(policy: &RetryPolicy) -> Vec<Duration> {
+        (1..policy.max_attempts)
+            .map(|attempt| policy.next_delay(attempt))
+            .collect()
+    }
+
+    #[test]
+    fn test_fixed_delays() {
+        let policy = RetryPolicy::fixed(4, Duration::from_millis(200));
+        assert_eq!(delays(&policy), vec![Duration::from_millis(200); 3]);
+        assert_eq!(policy.retries(), 3);
+        assert!(policy.should_retry(3));
+        assert!(!policy.should_retry(4));
+    }
+
+    #[test]
+    fn test_exponential_delays() {
+        let policy = RetryPolicy::exponential(5, Duration::from_millis(100));
+        let expected: Vec<_> = [100, 200, 400, 800]
+            .into_iter()
+            .map(Duration::from_millis)
+            .collect();
+        assert_eq!(delays(&policy), expected);
+
+        // Huge attempt counts saturate instead of overflowing
+        assert!(policy.next_delay(1000) >= Duration::from_secs(3600));
+    }
+
+    #[test]
+    fn test_jitter_stays_within_half_and_full_delay() {
+        let fixed = RetryPolicy::fixed(3, Duration::from_millis(200)).with_jitter(true);
+        let exponential = RetryPolicy::exponential(5, Duration::from_millis(100)).with_jitter(true);
+
+        for _ in 0..50 {
+            for delay in delays(&fixed) {
+                assert!(delay >= Duration::from_millis(100) && delay <= Duration::from_millis(200));
+            }
+            for (attempt, delay) in delays(&exponential).into_iter().enumerate() {
+                let full = Duration::from_millis(100 << attempt);
+                assert!(
+                    delay >= full / 2 && delay <= full,
+                    "{:?} vs {:?}",
+                    delay,
+                    full
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_none_never_retries() {
+        let policy = RetryPolicy::none();
+        assert_eq!(policy.max_attempts, 1);
+        assert!(!policy.should_retry(1));
+        assert!(delays(&policy).is_empty());
+    }
+}
@@ -0,0 +1,201 @@
+//! Round-trip integrity checks for the save formats
+//!
+//! Saves proxies in each format [`ProxyParser`] writes, parses the file back
+//! and reports any proxy that didn't survive, so a format change that loses
+//! credentials or mangles IPv6 hosts shows up before it reaches a user's list.
+
+use crate::proxy::models::{FullFormat, Proxy, ProxyType};
+use crate::proxy::parser::ProxyParser;
+use crate::Result;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+/// A file format proxies are saved in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SaveFormat {
+    /// One proxy per line in the given field order
+    Lines(FullFormat),
+    Json,
+    Yaml,
+    Csv,
+}
+
+impl SaveFormat {
+    /// Every format the round trip covers
+    pub const ALL: [SaveFormat; 6] = [
+        SaveFormat::Lines(FullFormat::ColonAll),
+        SaveFormat::Lines(FullFormat::AuthAt),
+        SaveFormat::Lines(FullFormat::Url),
+        SaveFormat::Json,
+        SaveFormat::Yaml,
+        SaveFormat::Csv,
+    ];
+
+    /// Whether the proxy type is written out
+    ///
+    /// Only `url` lines, proxy objects and CSV rows carry it; other lines read back
+    /// with the parser's default type.
+    pub fn keeps_type(self) -> bool {
+        !matches!(
+            self,
+            SaveFormat::Lines(FullFormat::ColonAll | FullFormat::AuthAt)
+        )
+    }
+
+    fn extension(self) -> &'static str {
+        match self {
+            SaveFormat::Lines(_) => "txt",
+            SaveFormat::Json => "json",
+            SaveFormat::Yaml => "yaml",
+            SaveFormat::Csv => "csv",
+        }
+    }
+}
+
+impl fmt::Display for SaveFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            SaveFormat::Lines(FullFormat::ColonAll) => "colon-all",
+            SaveFormat::Lines(FullFormat::AuthAt) => "auth-at",
+            SaveFormat::Lines(FullFormat::Url) => "url",
+            SaveFormat::Json => "json",
+            SaveFormat::Yaml => "yaml",
+            SaveFormat::Csv => "csv",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Outcome of saving proxies in one format and parsing them back
+#[derive(Debug, Clone)]
+pub struct RoundTrip {
+    pub format: SaveFormat,
+    /// Saved proxies that didn't come back
+    pub missing: Vec<Proxy>,
+    /// Parsed proxies that weren't saved
+    pub unexpected: Vec<Proxy>,
+}
+
+impl RoundTrip {
+    pub fn is_identical(&self) -> bool {
+        self.missing.is_empty() && self.unexpected.is_empty()
+    }
+}
+
+/// Save `proxies` to a file in `dir` in `format`, parse it back and compare
+///
+/// For formats that don't keep the proxy type (see
+/// [`SaveFormat::keeps_type`]) only endpoints and credentials are compared.
+/// The file is removed afterwards.
+pub fn round_trip(proxies: &[Proxy], format: SaveFormat, dir: &Path) -> Result<RoundTrip> {
+    let path = dir.join(format!(
+        "open-proxy-roundtrip-{}.{}",
+        uuid::Uuid::new_v4(),
+        format.extension()
+    ));
+    match format {
+        SaveFormat::Lines(full_format) => {
+            ProxyParser::save_to_file_with_format(proxies, &path, full_format)?
+        }
+        SaveFormat::Json => ProxyParser::save_json(proxies, &path, true)?,
+        SaveFormat::Yaml => ProxyParser::save_yaml(proxies, &path, true)?,
+        SaveFormat::Csv => ProxyParser::save_csv(proxies, &path, true)?,
+    }
+    let parsed = ProxyParser::parse_file(&path, ProxyType::Http);
+    fs::remove_file(&path)?;
+
+    let normalize = |proxy: &Proxy| {
+        let mut proxy = proxy.clone();
+        if !format.keeps_type() {
+            proxy.proxy_type = ProxyType::Http;
+        }
+        proxy
+    };
+    let saved: Vec<Proxy> = proxies.iter().map(normalize).collect();
+    let parsed: Vec<Proxy> = parsed?.iter().map(normalize).collect();
+
+    Ok(RoundTrip {
+        format,
+        missing: saved
+            .iter()
+            .filter(|p| !parsed.contains(p))
+            .cloned()
+            .collect(),
+        unexpected: parsed
+            .iter()
+            .filter(|p| !saved.contains(p))
+            .cloned()
+            .collect(),
+    })
+}
+
+/// Run [`round_trip`] for every format in [`SaveFormat::ALL`]
+pub fn round_trip_all(proxies: &[Proxy], dir: &Path) -> Result<Vec<RoundTrip>> {
+    SaveFormat::ALL
+        .iter()
+        .map(|&format| round_trip(proxies, format, dir))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> Vec<Proxy> {
+        vec![
+            Proxy::new("10.0.0.1".to_string(), 8080, ProxyType::Http),
+            Proxy::with_auth(
+                "10.0.0.2".to_string(),
+                1080,
+                ProxyType::Socks5,
+                "user".to_string(),
+                "pass".to_string(),
+            ),
+            Proxy::new("2001:db8::1".to_string(), 3128, ProxyType::Https),
+            Proxy::with_auth(
+                "2001:db8::2".to_string(),
+                1080,
+                ProxyType::Socks4,
+                "alice".to_string(),
+                "s3@cret".to_string(),
+            ),
+            Proxy::new("proxy.example.com".to_string(), 80, ProxyType::Http),
+        ]
+    }
+
+    #[test]
+    fn test_every_format_round_trips_auth_and_ipv6() {
+        for result in round_trip_all(&sample(), &std::env::temp_dir()).unwrap() {
+            assert!(result.is_identical(), "{}: {:?}", result.format, result);
+        }
+    }
+
+    #[test]
+    fn test_round_trip_reports_mismatches() {
+        // A colon in the password can't survive the colon-separated format
+        let proxies = vec![Proxy::with_auth(
+            "10.0.0.3".to_string(),
+            8080,
+            ProxyType::Http,
+            "user".to_string(),
+            "pa:ss".to_string(),
+        )];
+
+        let colon = round_trip(
+            &proxies,
+            SaveFormat::Lines(FullFormat::ColonAll),
+            &std::env::temp_dir(),
+        )
+        .unwrap();
+        assert_eq!(colon.missing, proxies);
+
+        let url = round_trip(
+            &proxies,
+            SaveFormat::Lines(FullFormat::Url),
+            &std::env::temp_dir(),
+        )
+        .unwrap();
+        assert!(url.is_identical(), "{:?}", url);
+    }
+}
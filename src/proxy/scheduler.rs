@@ -0,0 +1,121 @@
+//! Adaptive recheck scheduling based on a proxy's recent reliability
+
+use std::time::Duration;
+
+/// Default shortest interval between rechecks (5 minutes)
+const DEFAULT_MIN_INTERVAL: Duration = Duration::from_secs(5 * 60);
+
+/// Default longest interval between rechecks (1 hour)
+const DEFAULT_MAX_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// Default number of recent checks considered
+const DEFAULT_HISTORY_WINDOW: usize = 10;
+
+/// Scheduler that rechecks stable proxies less often than flaky ones
+///
+/// The interval grows linearly with the share of successful checks in the
+/// recent history: a proxy that always works waits `max_interval`, one that
+/// never works waits `min_interval`.
+#[derive(Debug, Clone)]
+pub struct AdaptiveScheduler {
+    /// Interval for proxies with no successful recent checks
+    pub min_interval: Duration,
+    /// Interval for proxies whose recent checks all succeeded
+    pub max_interval: Duration,
+    /// Number of most recent checks used to compute reliability
+    pub history_window: usize,
+}
+
+impl Default for AdaptiveScheduler {
+    fn default() -> Self {
+        Self {
+            min_interval: DEFAULT_MIN_INTERVAL,
+            max_interval: DEFAULT_MAX_INTERVAL,
+            history_window: DEFAULT_HISTORY_WINDOW,
+        }
+    }
+}
+
+impl AdaptiveScheduler {
+    pub fn new(min_interval: Duration, max_interval: Duration) -> Self {
+        Self {
+            min_interval,
+            max_interval: max_interval.max(min_interval),
+            history_window: DEFAULT_HISTORY_WINDOW,
+        }
+    }
+
+    pub fn with_history_window(mut self, window: usize) -> Self {
+        self.history_window = window.max(1);
+        self
+    }
+
+    /// Share of successful checks in `history` (newest first), from 0.0 to 1.0
+    ///
+    /// Only the first `history_window` entries are considered. An empty
+    /// history counts as unreliable so new proxies are rechecked soon.
+    pub fn reliability(&self, history: &[bool]) -> f64 {
+        let recent = &history[..history.len().min(self.history_window)];
+        if recent.is_empty() {
+            return 0.0;
+        }
+        recent.iter().filter(|&&working| working).count() as f64 / recent.len() as f64
+    }
+
+    /// Interval until the next check for a proxy with the given history
+    pub fn next_interval(&self, history: &[bool]) -> Duration {
+        let span = self.max_interval.saturating_sub(self.min_interval);
+        self.min_interval + span.mul_f64(self.reliability(history))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scheduler() -> AdaptiveScheduler {
+        AdaptiveScheduler::new(Duration::from_secs(60), Duration::from_secs(600))
+    }
+
+    #[test]
+    fn test_reliability() {
+        let scheduler = scheduler();
+        assert_eq!(scheduler.reliability(&[]), 0.0);
+        assert_eq!(scheduler.reliability(&[true, true, false, false]), 0.5);
+        assert_eq!(scheduler.reliability(&[true; 4]), 1.0);
+    }
+
+    #[test]
+    fn test_next_interval_bounds() {
+        let scheduler = scheduler();
+        assert_eq!(
+            scheduler.next_interval(&[true; 5]),
+            Duration::from_secs(600)
+        );
+        assert_eq!(
+            scheduler.next_interval(&[false; 5]),
+            Duration::from_secs(60)
+        );
+        assert_eq!(scheduler.next_interval(&[]), Duration::from_secs(60));
+    }
+
+    #[test]
+    fn test_next_interval_moves_with_reliability() {
+        let scheduler = scheduler();
+        let flaky = scheduler.next_interval(&[true, false, false, false]);
+        let mixed = scheduler.next_interval(&[true, true, false, false]);
+        let stable = scheduler.next_interval(&[true, true, true, false]);
+
+        assert!(flaky < mixed);
+        assert!(mixed < stable);
+        assert_eq!(mixed, Duration::from_secs(330));
+    }
+
+    #[test]
+    fn test_history_window_ignores_older_checks() {
+        let scheduler = scheduler().with_history_window(2);
+        // Only the two newest checks (both working) are considered
+        let history = [true, true, false, false, false];
+        assert_eq!(scheduler.next_interval(&history), Duration::from_secs(600));
+    }
+}
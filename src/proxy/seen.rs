@@ -0,0 +1,223 @@
+//! Persistent set of endpoints seen across runs
+
+use crate::proxy::models::Proxy;
+use crate::Result;
+use std::collections::{HashSet, VecDeque};
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+/// Default number of endpoints remembered before the oldest are forgotten
+pub const DEFAULT_MAX_SEEN: usize = 1_000_000;
+
+/// Endpoints (`host:port`) seen before, backed by an append-only log file
+///
+/// The log holds one hash per line, oldest first. Once it grows past
+/// `max_entries`, it is rewritten with only the newest three quarters of the
+/// bound, so an endpoint not seen for a long time may be emitted again.
+pub struct SeenSet {
+    hashes: HashSet<u64>,
+    order: VecDeque<u64>,
+    log: Option<(PathBuf, BufWriter<File>)>,
+    max_entries: usize,
+}
+
+impl SeenSet {
+    /// A set that is forgotten when dropped
+    pub fn in_memory() -> Self {
+        Self {
+            hashes: HashSet::new(),
+            order: VecDeque::new(),
+            log: None,
+            max_entries: usize::MAX,
+        }
+    }
+
+    /// Load the log at `path` (created if missing) and append to it from now on
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let mut set = Self::in_memory();
+        set.max_entries = DEFAULT_MAX_SEEN;
+
+        if path.exists() {
+            for line in BufReader::new(File::open(&path)?).lines() {
+                // Skip lines torn by an interrupted write
+                if let Ok(hash) = u64::from_str_radix(line?.trim(), 16) {
+                    set.remember(hash);
+                }
+            }
+        }
+
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        set.log = Some((path, BufWriter::new(file)));
+        Ok(set)
+    }
+
+    /// Remember at most `max_entries` endpoints (at least 1)
+    pub fn with_max_entries(mut self, max_entries: usize) -> Result<Self> {
+        self.max_entries = max_entries.max(1);
+        self.rotate_if_full()?;
+        Ok(self)
+    }
+
+    pub fn len(&self) -> usize {
+        self.hashes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.hashes.is_empty()
+    }
+
+    pub fn contains(&self, proxy: &Proxy) -> bool {
+        self.hashes.contains(&endpoint_hash(proxy))
+    }
+
+    /// Record `proxy`'s endpoint, returning whether it was never seen before
+    pub fn insert(&mut self, proxy: &Proxy) -> Result<bool> {
+        let hash = endpoint_hash(proxy);
+        if !self.remember(hash) {
+            return Ok(false);
+        }
+        if let Some((_, writer)) = &mut self.log {
+            writeln!(writer, "{:016x}", hash)?;
+        }
+        self.rotate_if_full()?;
+        Ok(true)
+    }
+
+    /// Write pending entries to the log
+    pub fn flush(&mut self) -> Result<()> {
+        if let Some((_, writer)) = &mut self.log {
+            writer.flush()?;
+        }
+        Ok(())
+    }
+
+    fn remember(&mut self, hash: u64) -> bool {
+        let new = self.hashes.insert(hash);
+        if new {
+            self.order.push_back(hash);
+        }
+        new
+    }
+
+    /// Forget the oldest entries and rewrite the log once it exceeds the bound
+    fn rotate_if_full(&mut self) -> Result<()> {
+        if self.order.len() <= self.max_entries {
+            return Ok(());
+        }
+
+        let keep = self.max_entries - self.max_entries / 4;
+        while self.order.len() > keep {
+            if let Some(hash) = self.order.pop_front() {
+                self.hashes.remove(&hash);
+            }
+        }
+
+        if let Some((path, writer)) = &mut self.log {
+            writer.flush()?;
+            // Write the new log next to the old one, then swap it in
+            let tmp = path.with_extension("tmp");
+            {
+                let mut out = BufWriter::new(File::create(&tmp)?);
+                for hash in &self.order {
+                    writeln!(out, "{:016x}", hash)?;
+                }
+                out.flush()?;
+            }
+            fs::rename(&tmp, &*path)?;
+            *writer = BufWriter::new(OpenOptions::new().append(true).open(&*path)?);
+        }
+        Ok(())
+    }
+}
+
+impl Drop for SeenSet {
+    fn drop(&mut self) {
+        let _ = self.flush();
+    }
+}
+
+/// Stable 64-bit FNV-1a hash of `host:port`, identical across runs and builds
+fn endpoint_hash(proxy: &Proxy) -> u64 {
+    const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0000_0100_0000_01b3;
+
+    format!("{}:{}", proxy.host.to_ascii_lowercase(), proxy.port)
+        .bytes()
+        .fold(OFFSET_BASIS, |hash, byte| {
+            (hash ^ byte as u64).wrapping_mul(PRIME)
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proxy::models::ProxyType;
+
+    fn proxy(host: &str, port: u16) -> Proxy {
+        Proxy::new(host.to_string(), port, ProxyType::Http)
+    }
+
+    fn temp_log() -> PathBuf {
+        std::env::temp_dir().join(format!("open-proxy-seen-{}.log", uuid::Uuid::new_v4()))
+    }
+
+    #[test]
+    fn test_seen_set_persists_across_opens() {
+        let path = temp_log();
+        {
+            let mut seen = SeenSet::open(&path).unwrap();
+            assert!(seen.insert(&proxy("10.0.0.1", 8080)).unwrap());
+            assert!(!seen.insert(&proxy("10.0.0.1", 8080)).unwrap());
+            assert!(seen.insert(&proxy("10.0.0.2", 8080)).unwrap());
+        }
+
+        let mut seen = SeenSet::open(&path).unwrap();
+        assert_eq!(seen.len(), 2);
+        // The same endpoint under another type is still a repeat
+        let mut socks = proxy("10.0.0.1", 8080);
+        socks.proxy_type = ProxyType::Socks5;
+        assert!(!seen.insert(&socks).unwrap());
+        assert!(seen.insert(&proxy("10.0.0.1", 3128)).unwrap());
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_seen_set_rotates_oldest_entries() {
+        let path = temp_log();
+        let mut seen = SeenSet::open(&path).unwrap().with_max_entries(4).unwrap();
+        for i in 1..=5 {
+            assert!(seen.insert(&proxy(&format!("10.0.0.{}", i), 80)).unwrap());
+        }
+
+        // Past the bound of 4, only the newest 3 are kept
+        assert_eq!(seen.len(), 3);
+        assert!(!seen.contains(&proxy("10.0.0.2", 80)));
+        assert!(seen.contains(&proxy("10.0.0.5", 80)));
+        seen.flush().unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap().lines().count(), 3);
+
+        // Appends after the rotation still land in the log
+        assert!(seen.insert(&proxy("10.0.0.6", 80)).unwrap());
+        drop(seen);
+        let seen = SeenSet::open(&path).unwrap();
+        assert_eq!(seen.len(), 4);
+        assert!(seen.contains(&proxy("10.0.0.6", 80)));
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_endpoint_hash_is_stable() {
+        assert_eq!(
+            endpoint_hash(&proxy("1.2.3.4", 8080)),
+            0xf242_4658_ca37_eced
+        );
+        assert_eq!(
+            endpoint_hash(&proxy("Example.COM", 80)),
+            endpoint_hash(&proxy("example.com", 80))
+        );
+    }
+}
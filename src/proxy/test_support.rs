@@ -0,0 +1,161 @@
+//! Helpers shared by unit tests that need a local HTTP endpoint
+
+#![allow(dead_code)]
+
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::TcpListener;
+
+/// A request received by the mock server
+#[derive(Debug, Clone)]
+pub struct MockRequest {
+    /// Address the request came from
+    pub peer: SocketAddr,
+    pub method: String,
+    pub target: String,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl MockRequest {
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+}
+
+/// Reply sent by the mock server
+pub struct MockReply {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+    /// Drop the connection after this reply without announcing it
+    pub close: bool,
+    /// `Content-Length` announced instead of the body's real length
+    pub content_length: Option<usize>,
+}
+
+impl MockReply {
+    pub fn new(status: u16, body: impl Into<Vec<u8>>) -> Self {
+        Self {
+            status,
+            headers: Vec::new(),
+            body: body.into(),
+            close: false,
+            content_length: None,
+        }
+    }
+
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    pub fn close(mut self) -> Self {
+        self.close = true;
+        self
+    }
+
+    /// Announce `len` bytes but send only the body and close, so the
+    /// transfer is cut short
+    pub fn truncated(mut self, len: usize) -> Self {
+        self.content_length = Some(len);
+        self.close = true;
+        self
+    }
+}
+
+/// Spawn an HTTP/1.1 server on localhost answering every request with `handler`
+///
+/// Works both as a judge and as an HTTP proxy, since proxied requests are
+/// plain HTTP requests with an absolute target.
+pub async fn spawn_http_server<F>(handler: F) -> SocketAddr
+where
+    F: Fn(&MockRequest) -> MockReply + Send + Sync + 'static,
+{
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let handler = Arc::new(handler);
+
+    tokio::spawn(async move {
+        while let Ok((stream, peer)) = listener.accept().await {
+            let handler = Arc::clone(&handler);
+            tokio::spawn(async move {
+                let mut reader = BufReader::new(stream);
+                while let Some(request) = read_request(&mut reader, peer).await {
+                    let reply = handler(&request);
+                    let mut head = format!("HTTP/1.1 {} Mock\r\n", reply.status);
+                    for (name, value) in &reply.headers {
+                        head.push_str(&format!("{}: {}\r\n", name, value));
+                    }
+                    if reply.status != 101 {
+                        let len = reply.content_length.unwrap_or(reply.body.len());
+                        head.push_str(&format!("Content-Length: {}\r\n", len));
+                    }
+                    head.push_str("\r\n");
+
+                    let stream = reader.get_mut();
+                    if stream.write_all(head.as_bytes()).await.is_err()
+                        || stream.write_all(&reply.body).await.is_err()
+                    {
+                        break;
+                    }
+                    if reply.close || reply.status == 101 {
+                        break;
+                    }
+                }
+            });
+        }
+    });
+
+    addr
+}
+
+async fn read_request<R>(reader: &mut BufReader<R>, peer: SocketAddr) -> Option<MockRequest>
+where
+    R: tokio::io::AsyncRead + Unpin,
+{
+    let mut request_line = String::new();
+    if reader.read_line(&mut request_line).await.ok()? == 0 {
+        return None;
+    }
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next()?.to_string();
+    let target = parts.next()?.to_string();
+
+    let mut headers = Vec::new();
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line).await.ok()? == 0 {
+            return None;
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            headers.push((name.trim().to_string(), value.trim().to_string()));
+        }
+    }
+
+    let mut request = MockRequest {
+        peer,
+        method,
+        target,
+        headers,
+        body: Vec::new(),
+    };
+    if let Some(len) = request
+        .header("Content-Length")
+        .and_then(|v| v.parse::<usize>().ok())
+    {
+        let mut body = vec![0; len];
+        reader.read_exact(&mut body).await.ok()?;
+        request.body = body;
+    }
+
+    Some(request)
+}
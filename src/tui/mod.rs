@@ -1,7 +1,9 @@
 //! TUI module for terminal user interfaces
 
 mod proxy_checker;
+mod stats_dashboard;
 mod todo_app;
 
 pub use proxy_checker::ProxyCheckerApp;
+pub use stats_dashboard::{DashboardState, StatsDashboard, StatsSnapshot};
 pub use todo_app::App;
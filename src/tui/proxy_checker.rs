@@ -23,6 +23,22 @@ use tokio::time::Duration;
 /// Maximum number of recent proxies to keep for display
 const MAX_RECENT_PROXIES: usize = 100;
 
+/// Working proxies faster than this (in ms) are shown green
+const FAST_LATENCY_MS: u64 = 500;
+
+/// Working proxies faster than this (in ms) are shown yellow; slower ones red
+const SLOW_LATENCY_MS: u64 = 2000;
+
+/// Color for a working proxy based on its response time
+fn latency_color(response_time_ms: Option<u64>) -> Color {
+    match response_time_ms {
+        Some(ms) if ms < FAST_LATENCY_MS => Color::Green,
+        Some(ms) if ms < SLOW_LATENCY_MS => Color::Yellow,
+        Some(_) => Color::LightRed,
+        None => Color::Green,
+    }
+}
+
 /// Proxy checker TUI application state
 pub struct ProxyCheckerApp {
     /// Proxies to check
@@ -167,7 +183,11 @@ impl ProxyCheckerApp {
 
                         // Write to file immediately
                         if let Some(ref mut file) = good_file {
-                            writeln!(file, "{}", result.proxy.to_full_string())?;
+                            writeln!(
+                                file,
+                                "{}",
+                                result.as_proxy_with_verified_type().to_full_string()
+                            )?;
                             file.flush()?;
                         }
 
@@ -374,6 +394,12 @@ impl ProxyCheckerApp {
                 } else {
                     result.proxy.to_simple_string()
                 };
+                // Working proxies are shaded by latency; `color` applies to the rest
+                let color = if result.is_working() {
+                    latency_color(result.response_time_ms)
+                } else {
+                    color
+                };
                 ListItem::new(content).style(Style::default().fg(color))
             })
             .collect();
@@ -404,3 +430,18 @@ impl ProxyCheckerApp {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_latency_color() {
+        assert_eq!(latency_color(Some(120)), Color::Green);
+        assert_eq!(latency_color(Some(499)), Color::Green);
+        assert_eq!(latency_color(Some(500)), Color::Yellow);
+        assert_eq!(latency_color(Some(1999)), Color::Yellow);
+        assert_eq!(latency_color(Some(2000)), Color::LightRed);
+        assert_eq!(latency_color(None), Color::Green);
+    }
+}
@@ -0,0 +1,398 @@
+//! Read-only TUI dashboard summarizing the proxy database
+
+use crate::database::{ProxyDatabase, ProxyRecord};
+use crate::proxy::geo::{self, GeoLocator};
+use crate::proxy::Proxy;
+use crate::Result;
+use chrono::{DateTime, Utc};
+use crossterm::{
+    event::{self, Event, KeyCode, KeyEventKind},
+    execute,
+    terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
+};
+use ratatui::{
+    backend::{Backend, CrosstermBackend},
+    layout::{Alignment, Constraint, Direction, Layout},
+    style::{Color, Style},
+    widgets::{Block, Borders, List, ListItem, Paragraph},
+    Frame, Terminal,
+};
+use std::io;
+use std::time::Duration;
+
+/// Number of fastest proxies and countries shown
+const DEFAULT_TOP: usize = 10;
+
+/// What the database held at one point in time
+#[derive(Debug, Clone)]
+pub struct StatsSnapshot {
+    pub records: Vec<ProxyRecord>,
+    /// Total checks recorded so far, used to derive the check rate
+    pub total_checks: u64,
+    pub taken_at: DateTime<Utc>,
+}
+
+impl StatsSnapshot {
+    /// Read the current state of the database
+    pub async fn load(db: &ProxyDatabase) -> Result<Self> {
+        Ok(Self {
+            records: db.get_all_proxies().await?,
+            total_checks: db.count_checks().await?,
+            taken_at: Utc::now(),
+        })
+    }
+}
+
+/// Figures shown on the dashboard, updated from successive snapshots
+#[derive(Debug, Clone, Default)]
+pub struct DashboardState {
+    pub total: usize,
+    pub working: usize,
+    /// Working proxies per country, largest first (empty without a geolocator)
+    pub countries: Vec<(String, usize)>,
+    /// Working proxies per autonomous system, largest first (empty unless the
+    /// geolocator knows ASNs)
+    pub networks: Vec<(String, usize)>,
+    /// Fastest working proxies with their last response time
+    pub fastest: Vec<(Proxy, u64)>,
+    /// Checks recorded per minute since the previous snapshot
+    /// (`None` until two snapshots were seen)
+    pub checks_per_minute: Option<f64>,
+    pub last_refresh: Option<DateTime<Utc>>,
+    previous_checks: Option<(u64, DateTime<Utc>)>,
+    top: usize,
+}
+
+impl DashboardState {
+    /// Create an empty state listing the `top` fastest proxies and countries
+    pub fn new(top: usize) -> Self {
+        Self {
+            top,
+            ..Self::default()
+        }
+    }
+
+    /// Recompute every figure from a new snapshot
+    pub fn update(&mut self, snapshot: &StatsSnapshot, locator: Option<&dyn GeoLocator>) {
+        let working: Vec<&ProxyRecord> = snapshot
+            .records
+            .iter()
+            .filter(|r| r.last_working == Some(true))
+            .collect();
+        self.total = snapshot.records.len();
+        self.working = working.len();
+
+        (self.countries, self.networks) = match locator {
+            Some(locator) => {
+                let proxies: Vec<Proxy> = working.iter().map(|r| r.proxy.clone()).collect();
+                let mut countries = geo::count_by_country(&proxies, locator);
+                countries.truncate(self.top);
+                let mut networks = geo::count_by_network(&proxies, locator);
+                if networks
+                    .iter()
+                    .all(|(name, _)| name == geo::UNKNOWN_COUNTRY)
+                {
+                    networks.clear();
+                }
+                networks.truncate(self.top);
+                (countries, networks)
+            }
+            None => (Vec::new(), Vec::new()),
+        };
+
+        let mut fastest: Vec<(Proxy, u64)> = working
+            .iter()
+            .filter_map(|r| Some((r.proxy.clone(), r.last_response_time_ms?)))
+            .collect();
+        fastest.sort_by_key(|(_, ms)| *ms);
+        fastest.truncate(self.top);
+        self.fastest = fastest;
+
+        // A shrinking count (e.g. after `dead --delete`) restarts the rate
+        self.checks_per_minute = match self.previous_checks {
+            Some((checks, at)) if snapshot.total_checks >= checks => {
+                let elapsed = (snapshot.taken_at - at).num_milliseconds() as f64 / 60_000.0;
+                (elapsed > 0.0).then(|| (snapshot.total_checks - checks) as f64 / elapsed)
+            }
+            _ => None,
+        };
+        self.previous_checks = Some((snapshot.total_checks, snapshot.taken_at));
+        self.last_refresh = Some(snapshot.taken_at);
+    }
+
+    /// Plain-text rendering used by `stats` without `--watch`
+    pub fn summary_lines(&self) -> Vec<String> {
+        let mut lines = vec![format!(
+            "Proxies: {} ({} working)",
+            self.total, self.working
+        )];
+        if let Some(rate) = self.checks_per_minute {
+            lines.push(format!("Check rate: {:.1}/min", rate));
+        }
+        if !self.countries.is_empty() {
+            lines.push("Working by country:".to_string());
+            for (country, count) in &self.countries {
+                lines.push(format!("  {:>6}  {}", count, country));
+            }
+        }
+        if !self.networks.is_empty() {
+            lines.push("Working by network:".to_string());
+            for (network, count) in &self.networks {
+                lines.push(format!("  {:>6}  {}", count, network));
+            }
+        }
+        if !self.fastest.is_empty() {
+            lines.push("Fastest:".to_string());
+            for (proxy, ms) in &self.fastest {
+                lines.push(format!("  {:>6}ms  {}", ms, proxy.to_simple_string()));
+            }
+        }
+        lines
+    }
+}
+
+/// Live dashboard re-reading the database every refresh interval
+pub struct StatsDashboard {
+    db: ProxyDatabase,
+    locator: Option<Box<dyn GeoLocator>>,
+    interval: Duration,
+    state: DashboardState,
+}
+
+impl StatsDashboard {
+    pub fn new(db: ProxyDatabase, interval: Duration) -> Self {
+        Self {
+            db,
+            locator: None,
+            interval,
+            state: DashboardState::new(DEFAULT_TOP),
+        }
+    }
+
+    /// Show the per-country breakdown using `locator`
+    pub fn with_locator(mut self, locator: Box<dyn GeoLocator>) -> Self {
+        self.locator = Some(locator);
+        self
+    }
+
+    pub fn with_top(mut self, top: usize) -> Self {
+        self.state = DashboardState::new(top);
+        self
+    }
+
+    /// Run the dashboard until the user quits
+    pub async fn run(&mut self) -> Result<()> {
+        enable_raw_mode()?;
+        let mut stdout = io::stdout();
+        execute!(stdout, EnterAlternateScreen)?;
+        let backend = CrosstermBackend::new(stdout);
+        let mut terminal = Terminal::new(backend)?;
+
+        let result = self.run_app(&mut terminal).await;
+
+        disable_raw_mode()?;
+        execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
+        terminal.show_cursor()?;
+
+        result
+    }
+
+    async fn run_app<B: Backend>(&mut self, terminal: &mut Terminal<B>) -> Result<()> {
+        let mut next_refresh = tokio::time::Instant::now();
+        loop {
+            if tokio::time::Instant::now() >= next_refresh {
+                let snapshot = StatsSnapshot::load(&self.db).await?;
+                self.state.update(&snapshot, self.locator.as_deref());
+                next_refresh = tokio::time::Instant::now() + self.interval;
+            }
+
+            terminal.draw(|f| self.ui(f))?;
+
+            if event::poll(Duration::from_millis(200))? {
+                if let Event::Key(key) = event::read()? {
+                    if key.kind == KeyEventKind::Press
+                        && matches!(key.code, KeyCode::Char('q') | KeyCode::Esc)
+                    {
+                        return Ok(());
+                    }
+                }
+            }
+        }
+    }
+
+    fn ui(&self, f: &mut Frame) {
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .margin(1)
+            .constraints([
+                Constraint::Length(3), // Title
+                Constraint::Length(3), // Totals
+                Constraint::Min(0),    // Breakdowns
+                Constraint::Length(3), // Status bar
+            ])
+            .split(f.size());
+
+        let title = Paragraph::new("📊 Proxy Stats")
+            .style(Style::default().fg(Color::Cyan))
+            .alignment(Alignment::Center)
+            .block(Block::default().borders(Borders::ALL));
+        f.render_widget(title, chunks[0]);
+
+        let rate = match self.state.checks_per_minute {
+            Some(rate) => format!("{:.1} checks/min", rate),
+            None => "measuring check rate...".to_string(),
+        };
+        let totals = Paragraph::new(format!(
+            "Proxies: {}  |  Working: {}  |  {}",
+            self.state.total, self.state.working, rate
+        ))
+        .block(Block::default().borders(Borders::ALL).title("Totals"));
+        f.render_widget(totals, chunks[1]);
+
+        let columns = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+            .split(chunks[2]);
+
+        let fastest: Vec<ListItem> = self
+            .state
+            .fastest
+            .iter()
+            .map(|(proxy, ms)| {
+                ListItem::new(format!("{} ({}ms)", proxy.to_simple_string(), ms))
+                    .style(Style::default().fg(Color::Green))
+            })
+            .collect();
+        f.render_widget(
+            List::new(fastest).block(Block::default().borders(Borders::ALL).title("Fastest")),
+            columns[0],
+        );
+
+        let countries: Vec<ListItem> = if self.locator.is_none() {
+            vec![ListItem::new("Pass --mmdb to break down by country")]
+        } else {
+            self.state
+                .countries
+                .iter()
+                .map(|(country, count)| ListItem::new(format!("{:>6}  {}", count, country)))
+                .collect()
+        };
+        f.render_widget(
+            List::new(countries).block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .title("Working by country"),
+            ),
+            columns[1],
+        );
+
+        let refreshed = self
+            .state
+            .last_refresh
+            .map(|t| t.format("%H:%M:%S").to_string())
+            .unwrap_or_default();
+        let status = Paragraph::new(format!(
+            "Refreshed {} (every {}s) | Press 'q' to quit",
+            refreshed,
+            self.interval.as_secs()
+        ))
+        .style(Style::default().fg(Color::Yellow))
+        .block(Block::default().borders(Borders::ALL).title("Status"));
+        f.render_widget(status, chunks[3]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proxy::geo::GeoInfo;
+    use crate::proxy::ProxyType;
+    use std::net::IpAddr;
+
+    /// Places 10.0.0.0/8 in the US and nothing else
+    struct UsLocator;
+
+    impl GeoLocator for UsLocator {
+        fn lookup(&self, ip: IpAddr) -> Option<GeoInfo> {
+            match ip {
+                IpAddr::V4(v4) if v4.octets()[0] == 10 => Some(GeoInfo {
+                    country_code: Some("US".to_string()),
+                    ..GeoInfo::default()
+                }),
+                _ => None,
+            }
+        }
+    }
+
+    fn record(host: &str, working: Option<bool>, ms: Option<u64>) -> ProxyRecord {
+        ProxyRecord {
+            proxy: Proxy::new(host.to_string(), 8080, ProxyType::Http),
+            last_working: working,
+            last_response_time_ms: ms,
+            last_checked_at: None,
+            next_check_at: None,
+            tags: Vec::new(),
+        }
+    }
+
+    fn snapshot(records: Vec<ProxyRecord>, total_checks: u64, secs: i64) -> StatsSnapshot {
+        StatsSnapshot {
+            records,
+            total_checks,
+            taken_at: DateTime::from_timestamp(1_700_000_000 + secs, 0).unwrap(),
+        }
+    }
+
+    #[test]
+    fn test_update_from_successive_snapshots() {
+        let mut state = DashboardState::new(2);
+
+        state.update(
+            &snapshot(
+                vec![
+                    record("10.0.0.1", Some(true), Some(300)),
+                    record("10.0.0.2", Some(false), None),
+                    record("20.0.0.1", None, None),
+                ],
+                10,
+                0,
+            ),
+            Some(&UsLocator),
+        );
+        assert_eq!((state.total, state.working), (3, 1));
+        assert_eq!(state.checks_per_minute, None);
+        assert_eq!(state.countries, vec![("US".to_string(), 1)]);
+
+        // 30 more checks in 30 seconds; the failing proxy recovered
+        state.update(
+            &snapshot(
+                vec![
+                    record("10.0.0.1", Some(true), Some(300)),
+                    record("10.0.0.2", Some(true), Some(100)),
+                    record("20.0.0.1", Some(true), Some(200)),
+                ],
+                40,
+                30,
+            ),
+            Some(&UsLocator),
+        );
+        assert_eq!((state.total, state.working), (3, 3));
+        assert_eq!(state.checks_per_minute, Some(60.0));
+        let fastest: Vec<_> = state
+            .fastest
+            .iter()
+            .map(|(p, ms)| (p.host.as_str(), *ms))
+            .collect();
+        assert_eq!(fastest, vec![("10.0.0.2", 100), ("20.0.0.1", 200)]);
+        assert_eq!(
+            state.countries,
+            vec![("US".to_string(), 2), ("Unknown".to_string(), 1)]
+        );
+
+        // Checks were deleted in between: the rate restarts
+        state.update(&snapshot(Vec::new(), 5, 60), None);
+        assert_eq!(state.checks_per_minute, None);
+        assert!(state.countries.is_empty());
+        assert!(state.fastest.is_empty());
+    }
+}
@@ -195,15 +195,13 @@ impl App {
 
     async fn handle_editing_input(&mut self, key: KeyCode) -> Result<bool> {
         match key {
-            KeyCode::Enter => {
-                if !self.input.is_empty() {
-                    let todo = Todo::new(self.input.trim().to_string(), None);
-                    self.db.create_todo(&todo).await?;
-                    self.input.clear();
-                    self.input_mode = InputMode::Normal;
-                    self.refresh_todos().await?;
-                    self.status_message = "Todo added!".to_string();
-                }
+            KeyCode::Enter if !self.input.is_empty() => {
+                let todo = Todo::new(self.input.trim().to_string(), None);
+                self.db.create_todo(&todo).await?;
+                self.input.clear();
+                self.input_mode = InputMode::Normal;
+                self.refresh_todos().await?;
+                self.status_message = "Todo added!".to_string();
             }
             KeyCode::Char(c) => {
                 self.input.push(c);